@@ -0,0 +1,294 @@
+use crate::{
+    events::{
+        Data, Pointer,
+        codec::{OrderedData, OrderedDialog},
+        opcodes::{Shape, by_mnemonic},
+    },
+    helpers::decode_hex,
+};
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use std::collections::HashMap;
+
+// Assembles listings in the same format disasm prints, plus a few things that make writing new script easier:
+//
+// greet_peter:                  ; Any name works as a label. L_xxxx labels keep their symbol, so their dialog keys stay put.
+//     text        L_0031        ; Text always lives in eventdialog.toml, under the key of its label
+//     branch_eq   L_0003, 0x00000001
+//     op_26       ...           ; Any opcode can be written by number
+//     jump        done
+//
+// done:
+//     ret
+//
+// L_0031:
+//     string
+
+const INLINE_TEXT: &str = "Text can't be written inline, as it would never make it into eventdialog.toml. Add it there \
+                           under a new key, and point at an L_xxxx label of that key holding a bare `string`.";
+
+struct Symbols {
+    labels: HashMap<String, Pointer>,
+    next_symbol: Pointer,
+}
+
+impl Symbols {
+    const fn fresh(&mut self) -> Pointer {
+        let symbol = self.next_symbol;
+        self.next_symbol += 1;
+        symbol
+    }
+
+    fn get(&self, name: &str) -> Result<Pointer, String> {
+        self.labels
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("Unknown label: {name}"))
+    }
+
+    // Labels can be referenced before they show up, so collect all of them first
+    fn new(lines: &[(usize, &str)], dialog: &OrderedDialog) -> Result<Self, String> {
+        let names = lines
+            .iter()
+            .filter_map(|(line_number, line)| {
+                line.strip_suffix(':').map(|name| (*line_number, name))
+            })
+            .collect::<Vec<_>>();
+        let mut labels = HashMap::with_capacity(names.len());
+        for (line_number, name) in &names {
+            if let Some(symbol) = symbol_from_label(name)
+                && labels.insert((*name).to_owned(), symbol).is_some()
+            {
+                return Err(format!("Line {line_number}: Duplicate label {name}"));
+            }
+        }
+        let mut symbols = Self {
+            next_symbol: labels
+                .values()
+                .chain(dialog.keys())
+                .max()
+                .map_or(0, |max| max + 1),
+            labels,
+        };
+        for (line_number, name) in names {
+            if symbol_from_label(name).is_some() {
+                continue;
+            }
+            if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(format!("Line {line_number}: Bad label name {name}"));
+            }
+            let symbol = symbols.fresh();
+            if symbols.labels.insert(name.to_owned(), symbol).is_some() {
+                return Err(format!("Line {line_number}: Duplicate label {name}"));
+            }
+        }
+        Ok(symbols)
+    }
+}
+
+/// Turns a listing back into event data, along with its dialog. Dialog for plain `string` lines comes out of `dialog`
+/// under the same key as the label above it.
+pub fn assemble(
+    listing: &str,
+    dialog: OrderedDialog,
+) -> Result<(OrderedData, OrderedDialog), String> {
+    let lines = listing
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, strip_comment(line).trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect::<Vec<_>>();
+    let symbols = Symbols::new(&lines, &dialog)?;
+
+    let mut ordered_data = OrderedData::with_capacity(symbols.labels.len());
+    let mut current_section = None;
+    for (line_number, line) in lines {
+        let error = |message: String| format!("Line {line_number}: {message}");
+        if let Some(name) = line.strip_suffix(':') {
+            let symbol = symbols.get(name).map_err(error)?;
+            ordered_data.insert(symbol, Vec::with_capacity(16));
+            current_section = Some(symbol);
+            continue;
+        }
+        let section = current_section
+            .ok_or_else(|| error("Instructions have to come after a label".to_owned()))?;
+        let (mnemonic, untrimmed_operands) =
+            line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let operands = untrimmed_operands.trim();
+
+        let datum = match mnemonic.to_lowercase().as_str() {
+            "raw" | "zero" => {
+                let bytes = if mnemonic.eq_ignore_ascii_case("raw") {
+                    let hex = operands.split_whitespace().collect::<String>();
+                    decode_hex(&hex).map_err(|e| error(format!("Bad raw bytes: {e}")))?
+                } else {
+                    vec![0; usize::try_from(parse_number(operands).map_err(error)?).unwrap()]
+                };
+                // Keep consecutive raw lines together as one chunk, the same way they were parsed
+                let data = ordered_data.get_mut(&section).unwrap();
+                if let Some(Data::Unmanaged(previous)) = data.last_mut() {
+                    previous.extend(bytes);
+                    continue;
+                }
+                Data::Unmanaged(bytes)
+            }
+            "ptr" => Data::Ptr(symbols.get(operands).map_err(error)?),
+            "string" => {
+                let data = ordered_data.get(&section).unwrap();
+                if data.iter().any(|datum| matches!(datum, Data::String(_))) {
+                    return Err(error("Only one string is allowed per label".to_owned()));
+                }
+                if !operands.is_empty() {
+                    return Err(error(INLINE_TEXT.to_owned()));
+                }
+                if !dialog.contains_key(&section) {
+                    return Err(error(format!("No dialog found for key {section:04x}")));
+                }
+                Data::String(Rc::new(RefCell::new(Vec::new())))
+            }
+            _ => {
+                let opcode = by_mnemonic(mnemonic)
+                    .ok_or_else(|| error(format!("Unknown instruction: {mnemonic}")))?;
+                let split = split_operands(operands);
+                let wrong_count = || {
+                    error(format!(
+                        "{mnemonic} takes {}, but got {} operand(s)",
+                        opcode.shape.layout(),
+                        split.len()
+                    ))
+                };
+                match (opcode.shape, split.as_slice()) {
+                    (Shape::Ret, []) => Data::Ret,
+                    (Shape::Branch, [target]) => {
+                        Data::J(opcode.code, symbols.get(target).map_err(error)?)
+                    }
+                    (Shape::Call, [c, d, field_1, field_2, target]) => Data::Jal(
+                        opcode.code,
+                        parse_byte(c).map_err(error)?,
+                        parse_byte(d).map_err(error)?,
+                        parse_number(field_1).map_err(error)?,
+                        parse_number(field_2).map_err(error)?,
+                        symbols.get(target).map_err(error)?,
+                    ),
+                    (Shape::Cop, [c, d, target]) => Data::Cop(
+                        opcode.code,
+                        parse_byte(c).map_err(error)?,
+                        parse_byte(d).map_err(error)?,
+                        symbols.get(target).map_err(error)?,
+                    ),
+                    (Shape::Cop2, [c, field, target]) => Data::Cop2(
+                        opcode.code,
+                        parse_byte(c).map_err(error)?,
+                        parse_number(field).map_err(error)?,
+                        symbols.get(target).map_err(error)?,
+                    ),
+                    (Shape::Multi, [target, values @ ..]) => Data::Multi(
+                        opcode.code,
+                        symbols.get(target).map_err(error)?,
+                        values
+                            .iter()
+                            .map(|value| parse_number(value))
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(error)?,
+                    ),
+                    (Shape::Op, [c]) => Data::Op(opcode.code, parse_byte(c).map_err(error)?),
+                    (Shape::Ptr, [c, target]) => Data::OpPtr(
+                        opcode.code,
+                        parse_byte(c).map_err(error)?,
+                        symbols.get(target).map_err(error)?,
+                    ),
+                    // A label rather than a number makes the word a pointer that follows the label around
+                    (Shape::Word, [c, field]) => match parse_number(field) {
+                        Ok(number) => {
+                            Data::OpWord(opcode.code, parse_byte(c).map_err(error)?, number)
+                        }
+                        Err(_) => Data::OpPtr(
+                            opcode.code,
+                            parse_byte(c).map_err(error)?,
+                            symbols.get(field).map_err(error)?,
+                        ),
+                    },
+                    (Shape::Text, [target]) if target.starts_with('"') => {
+                        return Err(error(INLINE_TEXT.to_owned()));
+                    }
+                    (Shape::Text, [target]) => Data::TxtPtr(symbols.get(target).map_err(error)?),
+                    _ => return Err(wrong_count()),
+                }
+            }
+        };
+        ordered_data.get_mut(&section).unwrap().push(datum);
+    }
+    Ok((ordered_data, dialog))
+}
+
+fn parse_byte(operand: &str) -> Result<u8, String> {
+    u8::try_from(parse_number(operand)?)
+        .map_err(|e| format!("{operand} doesn't fit in a byte: {e}"))
+}
+
+fn parse_number(operand: &str) -> Result<u32, String> {
+    operand
+        .strip_prefix("0x")
+        .map_or_else(|| operand.parse(), |hex| u32::from_str_radix(hex, 16))
+        .map_err(|e| format!("Bad number {operand}: {e}"))
+}
+
+fn split_operands(operands: &str) -> Vec<&str> {
+    if operands.is_empty() {
+        return Vec::new();
+    }
+    operands.split(',').map(str::trim).collect()
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(code, _)| code)
+}
+
+fn symbol_from_label(name: &str) -> Option<Pointer> {
+    let hex = name.strip_prefix("L_")?;
+    Pointer::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OrderedDialog, assemble};
+    use crate::events::{
+        codec::{marshal_events, parse_events},
+        disasm::disassemble,
+        keys::KeyScheme,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn disassembly_assembles_back_to_the_same_bytes() {
+        crate::ENGRISH.get_or_init(|| false);
+        let bytes = [
+            0x12, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, // text L_...
+            0x1e, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, // jump to the ret
+            0x82, 0xa0, 0x82, 0xa2, b'\\', 0x00, 0x00, 0x00, // the string
+            0x0a, 0x00, 0x00, 0x00, // ret
+        ];
+        let (ordered_data, dialog) =
+            parse_events(&mut Cursor::new(bytes), u32::try_from(bytes.len()).unwrap()).unwrap();
+        let listing = disassemble("test", &ordered_data, Some(&dialog));
+        let (assembled, assembled_dialog) = assemble(&listing, dialog).unwrap();
+        let (rebuilt, report) = marshal_events(
+            assembled,
+            Some(assembled_dialog),
+            "test",
+            KeyScheme::CURRENT,
+        );
+        assert!(report.is_clean(), "{listing}");
+        assert_eq!(rebuilt, bytes, "{listing}");
+    }
+
+    #[test]
+    fn inline_text_is_refused() {
+        let error = assemble(
+            "start:\n    text \"Hi[End]\"\n    ret",
+            OrderedDialog::new(),
+        )
+        .unwrap_err();
+        assert!(error.contains("eventdialog.toml"), "got {error}");
+    }
+}
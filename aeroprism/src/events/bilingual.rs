@@ -0,0 +1,164 @@
+use crate::events::{
+    DialogItem, DialogString, Pointer,
+    codec::{OrderedData, OrderedDialog},
+    dialog_files, find_event_files,
+    gettext::dialog_text,
+    keys::script_symbols,
+    load_event,
+};
+use alloc::collections::BTreeMap;
+use log::{info, warn};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// The Japanese and English releases run the same scripts, but the English text has different lengths, so strings
+// end up at other offsets and with other keys. Walking both scripts side by side pairs every symbol up with its
+// counterpart, as long as the translation didn't add or drop any instructions.
+
+// Another release's dialog file for an event, which of its symbols each of this release's symbols is, and its dialog
+type Counterpart = (PathBuf, HashMap<Pointer, Pointer>, OrderedDialog);
+
+/// One string from both releases.
+#[derive(Serialize)]
+pub struct PairedString {
+    // Only there when the releases use different control codes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub control_codes: Option<ControlCodes>,
+    // Missing when the English event doesn't line up with the Japanese one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub en: Option<String>,
+    pub ja: String,
+}
+
+#[derive(Serialize)]
+pub struct ControlCodes {
+    pub en: String,
+    pub ja: String,
+}
+
+impl PairedString {
+    pub fn new(ja: &DialogString, en: Option<&DialogString>) -> Self {
+        let ja_codes = control_codes(ja);
+        Self {
+            control_codes: en
+                .map(control_codes)
+                .filter(|en_codes| *en_codes != ja_codes)
+                .map(|en_codes| ControlCodes {
+                    en: en_codes,
+                    ja: ja_codes,
+                }),
+            en: en.map(dialog_text),
+            ja: dialog_text(ja),
+        }
+    }
+}
+
+// Every tag in a string, in order, e.g. [Portrait86][More][End]
+fn control_codes(dialog_string: &DialogString) -> String {
+    dialog_string
+        .text
+        .iter()
+        .filter(|item| !matches!(item, DialogItem::String(_)))
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Pairs up the dialog of a Japanese and an English workspace string by string, for the bilingual command.
+pub fn pair_workspaces(
+    ja_workspace: &Path,
+    en_workspace: &Path,
+    out_path: &Path,
+) -> Result<(), io::Error> {
+    let mut strings = BTreeMap::new();
+    let (mut unmatched, mut differing) = (0, 0);
+    for (dat_name, slot, _) in dialog_files(ja_workspace)? {
+        let (_, ordered_data, dialog) = load_event(&ja_workspace.join(&dat_name).join(&slot))?;
+        let en_dialog =
+            counterpart_dialog(&ordered_data, &en_workspace.join(&dat_name).join(&slot))?;
+        if en_dialog.is_none() {
+            unmatched += 1;
+            warn!("{dat_name}/{slot}: the English event doesn't line up with the Japanese one");
+        }
+        for (symbol, dialog_string) in dialog.unwrap_or_default() {
+            let en = en_dialog
+                .as_ref()
+                .and_then(|en_strings| en_strings.get(&symbol));
+            let paired_string = PairedString::new(&dialog_string, en);
+            if paired_string.control_codes.is_some() {
+                differing += 1;
+            }
+            strings.insert(format!("{dat_name}/{slot}/{symbol:04x}"), paired_string);
+        }
+    }
+    fs::write(
+        out_path,
+        toml::to_string(&strings).map_err(io::Error::other)?,
+    )?;
+    info!(
+        "Paired up {} string(s) in {}, {differing} with different control codes. {unmatched} event(s) didn't line up.",
+        strings.len(),
+        out_path.display()
+    );
+    Ok(())
+}
+
+/// Maps the symbols of one release's event to the other's, or gives up if the scripts don't have the same shape.
+pub fn match_symbols(from: &OrderedData, to: &OrderedData) -> Option<HashMap<Pointer, Pointer>> {
+    let from_symbols = script_symbols(from);
+    let to_symbols = script_symbols(to);
+    if from_symbols.len() != to_symbols.len() {
+        return None;
+    }
+    let mut symbols = HashMap::with_capacity(from_symbols.len());
+    for (from_symbol, to_symbol) in from_symbols.into_iter().zip(to_symbols) {
+        match symbols.entry(from_symbol) {
+            Entry::Occupied(entry) if *entry.get() != to_symbol => return None,
+            Entry::Occupied(_) => {}
+            Entry::Vacant(entry) => {
+                entry.insert(to_symbol);
+            }
+        }
+    }
+    Some(symbols)
+}
+
+/// Another release's dialog for an event, keyed by this release's symbols. None if the other release doesn't have the
+/// event, or its script has a different shape.
+pub fn counterpart_dialog(
+    ordered_data: &OrderedData,
+    other_slot: &Path,
+) -> Result<Option<OrderedDialog>, io::Error> {
+    let Some((_, symbols, other_strings)) = counterpart_event(ordered_data, other_slot)? else {
+        return Ok(None);
+    };
+    Ok(Some(
+        symbols
+            .into_iter()
+            .filter_map(|(symbol, other_symbol)| {
+                Some((symbol, other_strings.get(&other_symbol)?.clone()))
+            })
+            .collect(),
+    ))
+}
+
+/// The counterpart of an event in another release: its dialog file, which of its symbols each of this release's symbols
+/// is, and its dialog. None if the other release doesn't have the event, or its script has a different shape.
+pub fn counterpart_event(
+    ordered_data: &OrderedData,
+    other_slot: &Path,
+) -> Result<Option<Counterpart>, io::Error> {
+    let Ok((_, other_dialog_path)) = find_event_files(other_slot) else {
+        return Ok(None);
+    };
+    let (_, other_data, other_dialog) = load_event(other_slot)?;
+    let (Some(symbols), Some(other_strings)) =
+        (match_symbols(ordered_data, &other_data), other_dialog)
+    else {
+        return Ok(None);
+    };
+    Ok(Some((other_dialog_path, symbols, other_strings)))
+}
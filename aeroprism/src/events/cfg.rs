@@ -0,0 +1,194 @@
+use crate::events::{
+    Data, Pointer,
+    codec::{OrderedData, OrderedDialog},
+    disasm::{label, mnemonic, snippet},
+    load_event, print_or_write, string_sections, successors,
+};
+use clap::ValueEnum;
+use std::{io, path::Path};
+
+// Every section that isn't a string becomes a node, listing the dialog it shows. Strings are left out, as their text
+// is already on the node showing them. Edges are wherever the script can go from each section, the same as
+// reachability follows it: the pointers of each instruction up to the first ret or jump, plus a dashed one into the
+// next section as laid out whenever nothing ends the section before then.
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+struct Edge {
+    fallthrough: bool,
+    from: Pointer,
+    kind: String,
+    to: Pointer,
+}
+
+/// Draws an event's control flow as a Graphviz DOT or Mermaid graph.
+pub fn build_graph(
+    title: &str,
+    ordered_data: &OrderedData,
+    dialog: Option<&OrderedDialog>,
+    format: GraphFormat,
+) -> String {
+    let string_sections = string_sections(ordered_data);
+
+    let mut nodes = Vec::with_capacity(ordered_data.len());
+    let mut edges = Vec::with_capacity(ordered_data.len());
+    for (section, data) in ordered_data
+        .iter()
+        .filter(|(section, _)| !string_sections.contains(section))
+    {
+        let mut lines = vec![label(*section)];
+        for datum in data {
+            if let Data::TxtPtr(pointer) = datum {
+                let text = dialog
+                    .and_then(|dialog_strings| dialog_strings.get(pointer))
+                    .map_or_else(|| label(*pointer), snippet);
+                lines.push(format!("\"{text}\""));
+            }
+        }
+        for (instruction, to) in successors(ordered_data, &string_sections, *section) {
+            edges.push(Edge {
+                fallthrough: instruction.is_none(),
+                from: *section,
+                kind: instruction
+                    .map(|datum| datum.opcode().map_or_else(|| "ptr".to_owned(), mnemonic))
+                    .unwrap_or_default(),
+                to,
+            });
+        }
+        nodes.push((*section, lines));
+    }
+
+    match format {
+        GraphFormat::Dot => format_dot(title, &nodes, &edges),
+        GraphFormat::Mermaid => format_mermaid(title, &nodes, &edges),
+    }
+}
+
+/// Draws an unpacked event's control flow, for the cfg command.
+pub fn graph_event(
+    event: &Path,
+    format: GraphFormat,
+    out_path: Option<&Path>,
+) -> Result<(), io::Error> {
+    let (title, ordered_data, dialog) = load_event(event)?;
+    let graph = build_graph(&title, &ordered_data, dialog.as_ref(), format);
+    print_or_write(&graph, out_path)
+}
+
+fn format_dot(title: &str, nodes: &[(Pointer, Vec<String>)], edges: &[Edge]) -> String {
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut graph = format!(
+        "digraph \"{}\" {{\n    node [shape=box, fontname=monospace];\n",
+        escape(title)
+    );
+    for (section, lines) in nodes {
+        // \l left-aligns each line
+        let mut text = String::with_capacity(64 * lines.len());
+        for line in lines {
+            text.push_str(&escape(line));
+            text.push_str("\\l");
+        }
+        graph.push_str(format!("    {} [label=\"{text}\"];\n", label(*section)).as_str());
+    }
+    for edge in edges {
+        let style = if edge.fallthrough {
+            "style=dashed".to_owned()
+        } else {
+            format!("label=\"{}\"", escape(&edge.kind))
+        };
+        graph.push_str(
+            format!(
+                "    {} -> {} [{style}];\n",
+                label(edge.from),
+                label(edge.to)
+            )
+            .as_str(),
+        );
+    }
+    graph.push_str("}\n");
+    graph
+}
+
+fn format_mermaid(title: &str, nodes: &[(Pointer, Vec<String>)], edges: &[Edge]) -> String {
+    // Mermaid has no escapes for quotes inside labels, only entity codes
+    let escape = |text: &str| {
+        text.replace('"', "#quot;")
+            .replace('<', "#lt;")
+            .replace('>', "#gt;")
+    };
+    let mut graph = format!("---\ntitle: \"{}\"\n---\nflowchart TD\n", escape(title));
+    for (section, lines) in nodes {
+        let text = lines
+            .iter()
+            .map(|line| escape(line))
+            .collect::<Vec<_>>()
+            .join("<br/>");
+        graph.push_str(format!("    {}[\"{text}\"]\n", label(*section)).as_str());
+    }
+    for edge in edges {
+        let arrow = if edge.fallthrough {
+            "-.->".to_owned()
+        } else {
+            format!("-->|{}|", escape(&edge.kind))
+        };
+        graph.push_str(format!("    {} {arrow} {}\n", label(edge.from), label(edge.to)).as_str());
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphFormat, build_graph};
+    use crate::events::codec::parse_events;
+    use std::io::Cursor;
+
+    const EVENT: [u8; 0x2c] = [
+        0x17, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, // branch_ne L_0014
+        0x17, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, // branch_ne L_0018
+        0x0a, 0x00, 0x00, 0x00, // ret
+        0x26, 0x00, 0x01, 0x00, // L_0014: op_26, carrying on into L_0018
+        0x12, 0x00, 0x00, 0x00, 0x24, 0x00, 0x00, 0x00, // L_0018: text L_0024
+        0x0a, 0x00, 0x00, 0x00, // ret
+        0x82, 0xa0, 0x82, 0xa2, b'\\', 0x00, 0x00, 0x00, // L_0024: the string
+    ];
+
+    fn graph(bytes: &[u8]) -> String {
+        crate::ENGRISH.get_or_init(|| false);
+        let (ordered_data, dialog) =
+            parse_events(&mut Cursor::new(bytes), u32::try_from(bytes.len()).unwrap()).unwrap();
+        build_graph("test", &ordered_data, Some(&dialog), GraphFormat::Dot)
+    }
+
+    #[test]
+    fn edges_follow_the_script() {
+        let graph = graph(&EVENT);
+        assert!(
+            graph.contains("L_0000 -> L_0014 [label=\"branch_ne\"]"),
+            "{graph}"
+        );
+        assert!(
+            graph.contains("L_0000 -> L_0018 [label=\"branch_ne\"]"),
+            "{graph}"
+        );
+        assert!(graph.contains("L_0014 -> L_0018 [style=dashed]"), "{graph}");
+        // Text shows up on its node rather than as an edge into the string
+        assert!(!graph.contains("L_0018 ->"), "{graph}");
+    }
+
+    #[test]
+    fn nothing_after_a_jump_has_edges() {
+        let mut bytes = EVENT;
+        // jump L_0014, leaving the branch to L_0018 after it dead
+        bytes[0] = 0x0b;
+        let graph = graph(&bytes);
+        assert!(
+            graph.contains("L_0000 -> L_0014 [label=\"jump\"]"),
+            "{graph}"
+        );
+        assert!(!graph.contains("L_0000 -> L_0018"), "{graph}");
+    }
+}
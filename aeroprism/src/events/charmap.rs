@@ -0,0 +1,124 @@
+use crate::helpers::decode_hex;
+use core::iter;
+use std::collections::HashMap;
+
+// A table in the Atlas/Cartographer .tbl style, one entry per line, mapping hex bytes to text:
+//
+// 80=é
+// 8182=th
+//
+// Entries go ahead of the built in SJIS and English tables, both when unpacking and when repacking, so a patched
+// font can give bytes new glyphs. Where several entries share text, the first one is used for repacking. Control
+// codes like [More] still win over any entry starting with the same byte, and so do spaces and the @ newline.
+
+/// A custom character table, loaded with `--charmap`.
+pub struct Charmap {
+    decodings: HashMap<Vec<u8>, String>,
+    encodings: HashMap<String, Vec<u8>>,
+    longest_bytes: usize,
+    longest_text: usize,
+}
+
+impl Charmap {
+    /// The longest entry for the start of `bytes`, as the number of bytes it covers and its text.
+    pub fn decode(&self, bytes: &[u8]) -> Option<(usize, &str)> {
+        (1..=self.longest_bytes.min(bytes.len()))
+            .rev()
+            .find_map(|length| {
+                let text = self.decodings.get(bytes.get(..length)?)?;
+                Some((length, text.as_str()))
+            })
+    }
+
+    /// Same as `decode`, but pulling the bytes after `first` from a string being decoded.
+    pub fn decode_next<I: Iterator<Item = u8> + Clone>(
+        &self,
+        first: u8,
+        rest: &mut I,
+    ) -> Option<&str> {
+        let upcoming = iter::once(first)
+            .chain(rest.clone().take(self.longest_bytes.saturating_sub(1)))
+            .collect::<Vec<_>>();
+        let (length, text) = self.decode(&upcoming)?;
+        for _ in 1..length {
+            rest.next();
+        }
+        Some(text)
+    }
+
+    /// The longest entry for the start of `text`, as the number of bytes of text it covers and its encoding.
+    pub fn encode(&self, text: &str) -> Option<(usize, &[u8])> {
+        text.char_indices()
+            .map(|(index, _)| index)
+            .skip(1)
+            .chain(iter::once(text.len()))
+            .take(self.longest_text)
+            .filter_map(|length| {
+                let encoded = self.encodings.get(text.get(..length)?)?;
+                Some((length, encoded.as_slice()))
+            })
+            .last()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut charmap = Self {
+            decodings: HashMap::new(),
+            encodings: HashMap::new(),
+            longest_bytes: 0,
+            longest_text: 0,
+        };
+        for (line_number, line) in (1..).zip(text.trim_start_matches('\u{feff}').lines()) {
+            // Atlas end tokens (*XX, /XX) and comments have no place in dialog
+            if line.trim().is_empty() || line.starts_with(['#', ';', '*', '/']) {
+                continue;
+            }
+            let (hex, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Line {line_number}: expected hex=text, but got {line}"))?;
+            let bytes = decode_hex(hex.trim())
+                .ok()
+                .filter(|bytes| !bytes.is_empty())
+                .ok_or_else(|| format!("Line {line_number}: {hex} isn't hex"))?;
+            if value.is_empty() {
+                return Err(format!("Line {line_number}: {hex} has no text"));
+            }
+            charmap.longest_bytes = charmap.longest_bytes.max(bytes.len());
+            charmap.longest_text = charmap.longest_text.max(value.chars().count());
+            charmap
+                .encodings
+                .entry(value.to_owned())
+                .or_insert_with(|| bytes.clone());
+            charmap.decodings.insert(bytes, value.to_owned());
+        }
+        Ok(charmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Charmap;
+
+    const TABLE: &str = "# Ligatures\n80=f\n81=fi\n8182=ffi\n83=é\n";
+
+    #[test]
+    fn decoding_takes_the_longest_match() {
+        let charmap = Charmap::parse(TABLE).unwrap();
+        assert_eq!(charmap.decode(&[0x81, 0x82, 0x80]), Some((2, "ffi")));
+        assert_eq!(charmap.decode(&[0x81, 0x80]), Some((1, "fi")));
+        assert_eq!(charmap.decode(&[0x84]), None);
+        let mut rest = [0x82, 0x83].into_iter();
+        assert_eq!(charmap.decode_next(0x81, &mut rest), Some("ffi"));
+        assert_eq!(rest.next(), Some(0x83), "only the matched byte is used up");
+    }
+
+    #[test]
+    fn encoding_takes_the_longest_match() {
+        let charmap = Charmap::parse(TABLE).unwrap();
+        assert_eq!(charmap.encode("ffin"), Some((3, [0x81, 0x82].as_slice())));
+        assert_eq!(charmap.encode("fin"), Some((2, [0x81].as_slice())));
+        assert_eq!(charmap.encode("fun"), Some((1, [0x80].as_slice())));
+        // Multi-byte characters count as one
+        assert_eq!(charmap.encode("éa"), Some((2, [0x83].as_slice())));
+        assert_eq!(charmap.encode("a"), None);
+    }
+}
@@ -3,6 +3,10 @@ use crate::{
     events::{
         BytesOrPointer, Color, ControlCode, Data, DataItems, DialogItem, DialogString,
         GUESTIMATED_LENGTH, Offset, Pointer, Portrait, UmanagedData,
+        keys::KeyScheme,
+        opcodes::{Shape, by_code},
+        prompts::link_choices,
+        relocate::{RelocationReport, find_relocations},
         sjis_map::{SJIS_STARTER_BYTES, byte_to_engrish, byte_to_sjis, word_to_sjis},
     },
     helpers::{encode_hex, hex_edit_encode},
@@ -26,10 +30,18 @@ pub type OrderedData = IndexMap<Pointer, Vec<Data>>;
 pub type DialogMap = BTreeMap<Pointer, DialogString>;
 pub type OrderedDialog = IndexMap<Pointer, DialogString>;
 
-#[expect(clippy::single_call_fn, reason = "readability")]
 pub fn parse_events<R: Seek + BufRead>(
     reader: &mut R,
     eof: Offset,
+) -> Result<(OrderedData, OrderedDialog), io::Error> {
+    parse_events_keyed(reader, eof, KeyScheme::CURRENT)
+}
+
+/// Same as `parse_events`, but with pointer symbols picked by the given scheme rather than the current one.
+pub fn parse_events_keyed<R: Seek + BufRead>(
+    reader: &mut R,
+    eof: Offset,
+    keys: KeyScheme,
 ) -> Result<(OrderedData, OrderedDialog), io::Error> {
     #[expect(
         unused_assignments,
@@ -84,15 +96,25 @@ pub fn parse_events<R: Seek + BufRead>(
         if bytes_remaining >= u32::try_from(current_u32.len()).unwrap() {
             reader.read_exact(&mut current_u32)?;
             match current_u32 {
-                [op, 0x00, _, 0x00]
+                [op, 0x00, c, 0x00]
                     if !misaligned
-                        && [0x26, 0x2a, 0x2b, 0x2e, 0x40, 0x42, 0x44, 0x4d, 0x54].contains(&op) =>
+                        && by_code(op).is_some_and(|opcode| {
+                            matches!(opcode.shape, Shape::Op | Shape::Ptr | Shape::Word)
+                        }) =>
                 {
-                    current_unmanaged.update(current_offset, current_u32);
-                    if [0x2a, 0x2e, 0x40, 0x42, 0x44, 0x4d, 0x54].contains(&op) {
-                        reader.read_exact(&mut current_u32)?;
-                        current_unmanaged.update(current_offset, current_u32);
-                    }
+                    current_unmanaged.finish(eof, &mut data_items);
+                    let datum = match by_code(op).map(|opcode| opcode.shape) {
+                        Some(Shape::Ptr) => {
+                            reader.read_exact(&mut current_u32)?;
+                            Data::OpPtr(op, c, u32::from_le_bytes(current_u32))
+                        }
+                        Some(Shape::Word) => {
+                            reader.read_exact(&mut current_u32)?;
+                            Data::OpWord(op, c, u32::from_le_bytes(current_u32))
+                        }
+                        _ => Data::Op(op, c),
+                    };
+                    data_items.insert(current_offset, eof, datum);
                 }
                 [0x0a, 0x00, 0x00, 0x00] if !misaligned => {
                     current_unmanaged.finish(eof, &mut data_items);
@@ -243,16 +265,18 @@ pub fn parse_events<R: Seek + BufRead>(
         dialog_items.insert(pointer, string_repr);
     }
 
-    Ok(data_items.into_ordered_data(dialog_items))
+    let (ordered_data, mut ordered_dialog) = data_items.into_ordered_data(dialog_items, keys);
+    link_choices(&ordered_data, &mut ordered_dialog);
+    Ok((ordered_data, ordered_dialog))
 }
 
-#[expect(clippy::single_call_fn, reason = "readability")]
 pub fn marshal_events(
     // original_data: &[u8],
     ordered_data: OrderedData,
     mut dialog_items: Option<OrderedDialog>,
     file_name: &str,
-) -> Vec<u8> {
+    scheme: KeyScheme,
+) -> (Vec<u8>, RelocationReport) {
     let mut offset_tracker: HashMap<Pointer, Offset> = HashMap::new();
     let mut est_offset: usize = 0;
     for (pointer, data) in &ordered_data {
@@ -279,7 +303,7 @@ pub fn marshal_events(
                         panic!("Fatal error: Missing dialog pointer object {pointer:04x}")
                     });
                 let mut string_bytes = Vec::with_capacity(256);
-                let DialogString { text, padded } = dialog_string;
+                let DialogString { text, padded, .. } = dialog_string;
                 for item in text {
                     string_bytes.extend(item.into_bytes());
                 }
@@ -299,6 +323,12 @@ pub fn marshal_events(
     }
 
     debug!("Offset tracker is {} items", offset_tracker.len());
+    // Sequential symbols say nothing about where a section started out
+    let mut report = if scheme == KeyScheme::Offset {
+        find_relocations(&ordered_data, &offset_tracker)
+    } else {
+        RelocationReport::default()
+    };
 
     let mut coalesced_data = coalesce_bytes(ordered_data);
     coalesced_data.shrink_to_fit();
@@ -322,11 +352,10 @@ pub fn marshal_events(
                         let bytes = (*offset).to_le_bytes();
                         data_out.extend(bytes);
                     } else {
-                        let msg = format!(
+                        report.invalid_pointers.push(format!(
                             "Got invalid pointer ({pointer:04x}) while in ({section_pointer:04x}) at [{:04x}]",
                             data_out.len()
-                        );
-                        error!("{msg}");
+                        ));
                     }
                 }
             }
@@ -347,7 +376,7 @@ pub fn marshal_events(
         }
     }
     data_out.shrink_to_fit();
-    data_out
+    (data_out, report)
 }
 
 fn debug_raw_string(raw_ps2_sjis_string: &[u8]) {
@@ -401,48 +430,40 @@ fn decode_psg2_string(mut raw_ps2_sjis_string: Vec<u8>) -> DialogString {
             | ControlCode::Hat
             | ControlCode::Value => dialog_string.push(DialogItem::ControlCode(cc)),
             ControlCode::Color => {
-                if let Some(number) = string_iter.next_if(u8::is_ascii_digit) {
-                    let val = DialogItem::Color(Color::from(number));
-                    dialog_string.push(val);
+                if let Some(color) = string_iter
+                    .peek()
+                    .and_then(|number| Color::try_from(*number).ok())
+                {
+                    string_iter.next();
+                    dialog_string.push(DialogItem::Color(color));
                 } else {
-                    error!(
-                        "Expected utf8 numeral after color code. Output data corruption is likely."
+                    // Kept as is, so whatever the game makes of it survives a round trip
+                    warn!(
+                        "Expected a numeral from 1 to 7 after a color code. Keeping it as a raw byte."
                     );
+                    dialog_string.push(DialogItem::Raw(vec![byte]));
                 }
             }
             ControlCode::Portrait => {
-                let mut portrait_numerals = Vec::with_capacity(2);
+                let mut portrait_numerals = String::with_capacity(2);
                 while let Some(number) = string_iter.next_if(u8::is_ascii_digit) {
-                    portrait_numerals.push(number);
+                    portrait_numerals.push(char::from(number));
                 }
-                let val = DialogItem::Portrait(Portrait(
-                    String::from_utf8(portrait_numerals)
-                        .unwrap_or_else(|err| {error!("Error decoding portrait numeral: {err}\n I'll give you a motavian instead."); "86".into()}),
-                ));
-                dialog_string.push(val);
+                dialog_string.push(DialogItem::Portrait(Portrait(portrait_numerals)));
             }
             ControlCode::None => {
-                let mut sjis_strings = Vec::with_capacity(40);
-                parse_next_sjis(&mut string_iter, &mut sjis_strings, byte);
+                parse_next_sjis(&mut string_iter, &mut dialog_string, byte);
                 while let Some(next_string_byte) = string_iter.next_if(|b| {
                     *b == b'@' || *b == b' ' || SJIS_STARTER_BYTES.binary_search(b).is_ok()
                 }) {
-                    parse_next_sjis(&mut string_iter, &mut sjis_strings, next_string_byte);
-                }
-
-                // let decoded_sjis_string = decode_string(&sjis_string).to_string();
-                if let Some(DialogItem::String(eis)) = dialog_string.last_mut() {
-                    for string in sjis_strings {
-                        eis.push_str(string);
-                    }
-                } else {
-                    dialog_string.push(DialogItem::String(sjis_strings.concat()));
+                    parse_next_sjis(&mut string_iter, &mut dialog_string, next_string_byte);
                 }
             }
         }
     }
     dialog_string.shrink_to_fit();
     DialogString {
+        choices: Vec::new(),
         text: dialog_string,
         padded: pad,
     }
@@ -570,29 +591,46 @@ fn pinpoint_terminator_offset<R: Seek + BufRead>(
 
 fn parse_next_sjis(
     string_iter: &mut Peekable<IntoIter<u8>>,
-    sjis_string: &mut Vec<&str>,
+    dialog_string: &mut Vec<DialogItem>,
     byte: u8,
 ) {
-    if byte == b' ' {
-        sjis_string.push(" ");
+    let text = if byte == b' ' {
+        " "
     } else if byte == b'@' {
-        sjis_string.push("\n");
+        "\n"
+    } else if let Some(string) = crate::CHARMAP
+        .get()
+        .and_then(|charmap| charmap.decode_next(byte, string_iter))
+    {
+        string
     } else if *crate::ENGRISH.get().unwrap()
         && let Some(string) = byte_to_engrish(byte)
     {
-        sjis_string.push(string);
+        string
     } else if let Some(string) = byte_to_sjis(byte) {
-        sjis_string.push(string);
+        string
     } else {
-        let next_byte = string_iter.next().unwrap_or_else(||
-            {error!("Expected another character to follow a SHIFTJIS double character, but the data is truncated.");
-            0x00}
-        );
-        let character = word_to_sjis([byte, next_byte]).unwrap_or_else(|| {
-            error!("Unexpected character code: 0x{byte:02x}");
-            "INVALIDCHARACTER"
-        });
-        sjis_string.push(character);
+        // Anything without a character is kept byte for byte, so it comes back out exactly as it went in
+        let Some(next_byte) = string_iter.next() else {
+            error!(
+                "Expected another character to follow a SHIFTJIS double character, but the data is truncated."
+            );
+            dialog_string.push(DialogItem::Raw(vec![byte]));
+            return;
+        };
+        let Some(character) = word_to_sjis([byte, next_byte]) else {
+            warn!(
+                "Unexpected character code: 0x{byte:02x}{next_byte:02x}. Keeping it as raw bytes."
+            );
+            dialog_string.push(DialogItem::Raw(vec![byte, next_byte]));
+            return;
+        };
+        character
+    };
+    if let Some(DialogItem::String(existing)) = dialog_string.last_mut() {
+        existing.push_str(text);
+    } else {
+        dialog_string.push(DialogItem::String(text.to_owned()));
     }
 }
 
@@ -679,6 +717,27 @@ fn coalesce_bytes(
                     data.push(BytesOrPointer::Bytes(bytes));
                     data.push(BytesOrPointer::Pointer(ref_pointer));
                 }
+                Data::Op(op, c) => {
+                    let data = chunked_data
+                        .entry(pointer)
+                        .or_insert(Vec::with_capacity(40));
+                    data.push(BytesOrPointer::Bytes(vec![op, 0x00, c, 0x00]));
+                }
+                Data::OpPtr(op, c, ref_pointer) => {
+                    let data = chunked_data
+                        .entry(pointer)
+                        .or_insert(Vec::with_capacity(40));
+                    data.push(BytesOrPointer::Bytes(vec![op, 0x00, c, 0x00]));
+                    data.push(BytesOrPointer::Pointer(ref_pointer));
+                }
+                Data::OpWord(op, c, field) => {
+                    let data = chunked_data
+                        .entry(pointer)
+                        .or_insert(Vec::with_capacity(40));
+                    data.push(BytesOrPointer::Bytes(
+                        [[op, 0x00, c, 0x00], field.to_le_bytes()].concat(),
+                    ));
+                }
                 Data::Ptr(ref_pointer) => {
                     let data = chunked_data
                         .entry(pointer)
@@ -696,3 +755,51 @@ fn coalesce_bytes(
     }
     chunked_data
 }
+
+#[cfg(test)]
+mod tests {
+    use super::decode_psg2_string;
+    use crate::events::{DialogItem, parse_dialog};
+
+    // Decodes a string, writes it out the way the dialog files show it, then reads that back and encodes it
+    fn round_trip(bytes: &[u8]) -> (String, Vec<u8>) {
+        crate::ENGRISH.get_or_init(|| false);
+        let text = decode_psg2_string(bytes.to_vec())
+            .text
+            .iter()
+            .map(ToString::to_string)
+            .collect::<String>();
+        let encoded = parse_dialog(&text)
+            .unwrap()
+            .into_iter()
+            .flat_map(DialogItem::into_bytes)
+            .collect();
+        (text, encoded)
+    }
+
+    #[test]
+    fn unknown_characters_round_trip_as_raw() {
+        let bytes = [0x82, 0xa0, 0x81, 0xff, 0x82, 0xa2, b'\\'];
+        let (text, encoded) = round_trip(&bytes);
+        assert!(text.contains("[Raw 81 ff]"), "got {text}");
+        assert_eq!(encoded, bytes, "{text} didn't encode back the same");
+    }
+
+    #[test]
+    fn bad_color_digits_round_trip_as_raw() {
+        let bytes = [b'c', b'?', 0x82, 0xa0, b'c', b'2', 0x82, 0xa2, b'\\'];
+        let (text, encoded) = round_trip(&bytes);
+        assert!(text.starts_with("[Raw 63][More]"), "got {text}");
+        assert!(text.contains("[Red]"), "got {text}");
+        assert_eq!(encoded, bytes, "{text} didn't encode back the same");
+    }
+
+    #[test]
+    fn empty_portraits_round_trip() {
+        let bytes = [b'#', 0x82, 0xa0, b'#', b'8', b'6', 0x82, 0xa2, b'\\'];
+        let (text, encoded) = round_trip(&bytes);
+        assert!(text.starts_with("[Portrait]"), "got {text}");
+        assert!(text.contains("[Portrait86]"), "got {text}");
+        assert_eq!(encoded, bytes, "{text} didn't encode back the same");
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{
+    events::{
+        Data, DialogString, Pointer,
+        codec::{OrderedData, OrderedDialog},
+        load_event,
+        opcodes::by_code,
+        print_or_write,
+    },
+    helpers::encode_hex,
+};
+use alloc::collections::{BTreeMap, BTreeSet};
+use std::{io, path::Path};
+use unicode_segmentation::UnicodeSegmentation;
+
+const SNIPPET_LENGTH: usize = 48;
+const RAW_WORDS_PER_LINE: usize = 8;
+const COMMENT_COLUMN: usize = 44;
+
+// Listing format, which the assembler reads back in:
+//
+// L_0000:
+//     text        L_0002              ; [Portrait86]Hello there[More]
+//     branch_eq   L_0003, 0x00000001
+//     ret
+//
+// L_0002:                             ; xref: L_0000
+//     string
+//
+// Labels are the pointer symbols, so `string` pulls its text from the dialog file under the same key. Bytes that aren't
+// understood yet are listed as `raw` words, with long runs of zeros shortened to `zero <count>`.
+
+/// Label for a pointer symbol, as used in listings.
+pub fn label(symbol: Pointer) -> String {
+    format!("L_{symbol:04x}")
+}
+
+/// Short one line preview of a dialog string, for comments.
+pub fn snippet(dialog_string: &DialogString) -> String {
+    let text = dialog_string
+        .text
+        .iter()
+        .map(ToString::to_string)
+        .collect::<String>()
+        .replace('\n', "\\n");
+    let mut graphemes = text.graphemes(true);
+    let mut snippet = graphemes.by_ref().take(SNIPPET_LENGTH).collect::<String>();
+    if graphemes.next().is_some() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Lists an unpacked event, for the disasm command.
+pub fn list_event(event: &Path, out_path: Option<&Path>) -> Result<(), io::Error> {
+    let (title, ordered_data, dialog) = load_event(event)?;
+    let listing = disassemble(&title, &ordered_data, dialog.as_ref());
+    print_or_write(&listing, out_path)
+}
+
+/// Lists an event as labeled pseudo-assembly, with each label cross-referenced to the sections that point at it.
+pub fn disassemble(
+    title: &str,
+    ordered_data: &OrderedData,
+    dialog: Option<&OrderedDialog>,
+) -> String {
+    let mut xrefs: BTreeMap<Pointer, BTreeSet<Pointer>> = BTreeMap::new();
+    for (section, data) in ordered_data {
+        for datum in data {
+            if let Some(pointer) = datum.get_pointer() {
+                xrefs.entry(pointer).or_default().insert(*section);
+            }
+        }
+    }
+
+    let mut listing = String::with_capacity(64 * ordered_data.len());
+    listing.push_str(format!("; {title}\n").as_str());
+    // Spell out every opcode this event uses up front, as most of them don't have names yet
+    let used_ops = ordered_data
+        .values()
+        .flatten()
+        .filter_map(Data::opcode)
+        .collect::<BTreeSet<_>>();
+    for op in used_ops {
+        if let Some(opcode) = by_code(op) {
+            listing.push_str(
+                format!(
+                    ";   {:<11} {:<33} {}\n",
+                    opcode.mnemonic,
+                    opcode.shape.layout(),
+                    opcode.description
+                )
+                .as_str(),
+            );
+        }
+    }
+    for (section, data) in ordered_data {
+        let mut label_line = format!("{}:", label(*section));
+        if let Some(section_xrefs) = xrefs.get(section) {
+            let referrers = section_xrefs
+                .iter()
+                .map(|referrer| label(*referrer))
+                .collect::<Vec<_>>()
+                .join(", ");
+            label_line = format!("{label_line:<COMMENT_COLUMN$}; xref: {referrers}");
+        }
+        listing.push('\n');
+        listing.push_str(label_line.trim_end());
+        listing.push('\n');
+        for datum in data {
+            for (instruction, maybe_comment) in format_data(*section, datum, dialog) {
+                let line = maybe_comment.map_or_else(
+                    || format!("    {instruction}"),
+                    |comment| format!("    {instruction:<40}; {comment}"),
+                );
+                listing.push_str(line.trim_end());
+                listing.push('\n');
+            }
+        }
+    }
+    listing
+}
+
+fn format_data(
+    section: Pointer,
+    datum: &Data,
+    dialog: Option<&OrderedDialog>,
+) -> Vec<(String, Option<String>)> {
+    let dialog_snippet =
+        |symbol: Pointer| dialog.and_then(|strings| strings.get(&symbol)).map(snippet);
+    let instruction = match datum {
+        Data::Ret => "ret".to_owned(),
+        Data::J(op, pointer) => format!("{:<11} {}", mnemonic(*op), label(*pointer)),
+        Data::Jal(op, c, d, field_1, field_2, pointer) => format!(
+            "{:<11} {c:#04x}, {d:#04x}, {field_1:#010x}, {field_2:#010x}, {}",
+            mnemonic(*op),
+            label(*pointer)
+        ),
+        Data::Multi(op, pointer, values) => {
+            let mut instruction = format!("{:<11} {}", mnemonic(*op), label(*pointer));
+            for value in values {
+                instruction.push_str(format!(", {value:#010x}").as_str());
+            }
+            instruction
+        }
+        Data::TxtPtr(pointer) => {
+            return vec![(
+                format!("{:<11} {}", "text", label(*pointer)),
+                dialog_snippet(*pointer),
+            )];
+        }
+        Data::String(_) => return vec![("string".to_owned(), dialog_snippet(section))],
+        Data::Cop(op, c, d, pointer) => format!(
+            "{:<11} {c:#04x}, {d:#04x}, {}",
+            mnemonic(*op),
+            label(*pointer)
+        ),
+        Data::Cop2(op, c, field, pointer) => format!(
+            "{:<11} {c:#04x}, {field:#010x}, {}",
+            mnemonic(*op),
+            label(*pointer)
+        ),
+        Data::Op(op, c) => format!("{:<11} {c:#04x}", mnemonic(*op)),
+        Data::OpPtr(op, c, pointer) => {
+            format!("{:<11} {c:#04x}, {}", mnemonic(*op), label(*pointer))
+        }
+        Data::OpWord(op, c, field) => format!("{:<11} {c:#04x}, {field:#010x}", mnemonic(*op)),
+        Data::Ptr(pointer) => format!("{:<11} {}", "ptr", label(*pointer)),
+        Data::Unmanaged(bytes) => return format_raw(bytes),
+    };
+    vec![(instruction, None)]
+}
+
+// Padding at the end of an event can run for a couple thousand bytes, so long runs of zeros get collapsed
+fn format_raw(bytes: &[u8]) -> Vec<(String, Option<String>)> {
+    let mut lines = Vec::with_capacity(bytes.len() / (4 * RAW_WORDS_PER_LINE) + 1);
+    let mut remaining = bytes;
+    while !remaining.is_empty() {
+        let zeros = remaining.iter().take_while(|&&b| b == 0).count();
+        if zeros >= 8 * RAW_WORDS_PER_LINE {
+            lines.push((format!("{:<11} {zeros:#x}", "zero"), None));
+            remaining = remaining.get(zeros..).unwrap_or_default();
+            continue;
+        }
+        let (line, rest) = remaining.split_at(remaining.len().min(4 * RAW_WORDS_PER_LINE));
+        let words = line.chunks(4).map(encode_hex).collect::<Vec<_>>();
+        lines.push((format!("{:<11} {}", "raw", words.join(" ")), None));
+        remaining = rest;
+    }
+    lines
+}
+
+/// Name of an opcode as it appears in listings.
+pub fn mnemonic(op: u8) -> String {
+    by_code(op).map_or_else(
+        || format!("op_{op:02x}"),
+        |opcode| opcode.mnemonic.to_owned(),
+    )
+}
@@ -0,0 +1,320 @@
+use crate::events::{
+    DialogString, IndexMapWrapper, bilingual::counterpart_dialog, dialog_files, find_event_files,
+    load_dialog_strings, load_event, parse_dialog, save_dialog_strings,
+};
+use alloc::collections::BTreeMap;
+use core::{fmt::Write, mem};
+use log::{info, warn};
+use std::{fs, io, path::Path};
+
+// gettext catalogs for translation tools like Weblate and Poedit. Each string is its own entry:
+//
+// #: EVENT.DAT/0012.lz77.eventdialog.toml
+// #, placeholders:r"\[[^\]]+\]"
+// msgctxt "EVENT.DAT/0012/01a4"
+// msgid ""
+// "[Portrait86]Hello there[More]\n"
+// "Nice weather![End]"
+// msgstr ""
+//
+// The placeholders flag has Weblate treat [Tags] as placeables, so they can't get mangled by accident. Tools that
+// don't know it just ignore it.
+
+const PLACEHOLDERS_FLAG: &str = r#"placeholders:r"\[[^\]]+\]""#;
+
+/// One dialog string in a catalog.
+#[derive(Default)]
+pub struct CatalogEntry {
+    // DAT/slot/key, e.g. EVENT.DAT/0012/01a4
+    pub context: String,
+    pub fuzzy: bool,
+    pub msgid: String,
+    pub msgstr: String,
+    // The dialog file the string came from
+    pub reference: String,
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Context,
+    Id,
+    Str,
+}
+
+/// Dialog text as it reads in the dialog files, tags and all.
+pub fn dialog_text(dialog_string: &DialogString) -> String {
+    dialog_string.text.iter().map(ToString::to_string).collect()
+}
+
+/// Replaces the text of a dialog string, with the same rules as the dialog files.
+pub fn set_dialog_text(dialog_string: &mut DialogString, text: &str) -> Result<(), String> {
+    dialog_string.text = parse_dialog(text)?;
+    Ok(())
+}
+
+/// A whole catalog, header included. Leaving every `msgstr` empty makes it a template.
+pub fn write_catalog(entries: &[CatalogEntry]) -> String {
+    let mut po = String::with_capacity(256 * (entries.len() + 1));
+    po.push_str("msgid \"\"\nmsgstr \"\"\n");
+    po.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+    po.push_str("\"Content-Transfer-Encoding: 8bit\\n\"\n");
+    po.push_str("\"X-Generator: aeroprism\\n\"\n");
+    for entry in entries {
+        _ = writeln!(po, "\n#: {}", entry.reference);
+        if entry.fuzzy {
+            _ = writeln!(po, "#, fuzzy, {PLACEHOLDERS_FLAG}");
+        } else {
+            _ = writeln!(po, "#, {PLACEHOLDERS_FLAG}");
+        }
+        po.push_str(&format_field("msgctxt", &entry.context));
+        po.push_str(&format_field("msgid", &entry.msgid));
+        po.push_str(&format_field("msgstr", &entry.msgstr));
+    }
+    po
+}
+
+/// Reads back a catalog. Only what aeroprism writes is supported, so no plurals.
+pub fn parse_catalog(text: &str) -> Result<Vec<CatalogEntry>, String> {
+    let mut entries = Vec::with_capacity(text.len() / 128);
+    let mut current = CatalogEntry::default();
+    let mut field = None;
+    let mut has_msgstr = false;
+    for (line_number, untrimmed) in (1..).zip(text.lines()) {
+        let line = untrimmed.trim();
+        let error = |message: String| format!("Line {line_number}: {message}");
+        let starts_entry = line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("msgctxt")
+            || line.starts_with("msgid");
+        if starts_entry && has_msgstr {
+            entries.push(mem::take(&mut current));
+            has_msgstr = false;
+            field = None;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(flags) = line.strip_prefix("#,") {
+            current.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+            continue;
+        }
+        if let Some(reference) = line.strip_prefix("#:") {
+            reference.trim().clone_into(&mut current.reference);
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let quoted = if line.starts_with('"') {
+            line
+        } else {
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            field = Some(match keyword {
+                "msgctxt" => Field::Context,
+                "msgid" => Field::Id,
+                "msgstr" => {
+                    has_msgstr = true;
+                    Field::Str
+                }
+                _ => return Err(error(format!("Unsupported keyword {keyword}"))),
+            });
+            rest.trim()
+        };
+        let value = unquote(quoted).map_err(error)?;
+        match field {
+            Some(Field::Context) => current.context.push_str(&value),
+            Some(Field::Id) => current.msgid.push_str(&value),
+            Some(Field::Str) => current.msgstr.push_str(&value),
+            None => return Err(error("Text outside of any msgid or msgstr".to_owned())),
+        }
+    }
+    if has_msgstr {
+        entries.push(current);
+    }
+    // The header is the entry without an id
+    entries.retain(|entry| !entry.msgid.is_empty());
+    Ok(entries)
+}
+
+/// Writes every dialog string in the workspace to a catalog, with the translation workspace's strings as msgstrs.
+pub fn export_catalog(
+    workspace: &Path,
+    translation: Option<&Path>,
+    out_path: &Path,
+) -> Result<(), io::Error> {
+    let mut entries = Vec::new();
+    let mut translated = 0;
+    for (dat_name, slot, file_name) in dialog_files(workspace)? {
+        let (_, ordered_data, dialog) = load_event(&workspace.join(&dat_name).join(&slot))?;
+        let translated_dialog = match translation {
+            Some(translation_workspace) => counterpart_dialog(
+                &ordered_data,
+                &translation_workspace.join(&dat_name).join(&slot),
+            )?,
+            None => None,
+        };
+        for (symbol, dialog_string) in dialog.unwrap_or_default() {
+            let msgstr = translated_dialog
+                .as_ref()
+                .and_then(|strings| strings.get(&symbol))
+                .map(dialog_text)
+                .unwrap_or_default();
+            if !msgstr.is_empty() {
+                translated += 1;
+            }
+            entries.push(CatalogEntry {
+                context: format!("{dat_name}/{slot}/{symbol:04x}"),
+                fuzzy: false,
+                msgid: dialog_text(&dialog_string),
+                msgstr,
+                reference: format!("{dat_name}/{file_name}"),
+            });
+        }
+    }
+    fs::write(out_path, write_catalog(&entries))?;
+    info!(
+        "Exported {} string(s), {translated} of them translated, to {}",
+        entries.len(),
+        out_path.display()
+    );
+    Ok(())
+}
+
+/// Writes a catalog's translated, non-fuzzy strings back into the workspace's dialog files.
+pub fn import_catalog(po_path: &Path, workspace: &Path) -> Result<(), io::Error> {
+    let entries = parse_catalog(&fs::read_to_string(po_path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", po_path.display()),
+        )
+    })?;
+    let mut dialog_files = BTreeMap::new();
+    let (mut imported, mut skipped) = (0, 0);
+    for entry in entries {
+        if entry.msgstr.is_empty() || entry.fuzzy {
+            skipped += 1;
+            continue;
+        }
+        // EVENT.DAT/0012/01a4
+        let mut parts = entry.context.rsplitn(3, '/');
+        let (Some(key), Some(slot), Some(dat_name)) = (parts.next(), parts.next(), parts.next())
+        else {
+            warn!("{}: not a DAT/slot/key context, skipping", entry.context);
+            continue;
+        };
+        let Ok(symbol) = u32::from_str_radix(key, 16) else {
+            warn!("{}: {key} isn't a dialog key, skipping", entry.context);
+            continue;
+        };
+        let Ok((_, dialog_path)) = find_event_files(&workspace.join(dat_name).join(slot)) else {
+            warn!(
+                "{}: no such event in the workspace, skipping",
+                entry.context
+            );
+            continue;
+        };
+        if !dialog_files.contains_key(&dialog_path) {
+            let dialog = load_dialog_strings(&dialog_path)?;
+            dialog_files.insert(dialog_path.clone(), dialog);
+        }
+        let Some(dialog_string) = dialog_files
+            .get_mut(&dialog_path)
+            .and_then(|dialog| dialog.get_mut(&symbol))
+        else {
+            warn!(
+                "{}: no such string in the workspace, skipping",
+                entry.context
+            );
+            continue;
+        };
+        match set_dialog_text(dialog_string, &entry.msgstr) {
+            Ok(()) => imported += 1,
+            Err(e) => warn!("{}: {e}, skipping", entry.context),
+        }
+    }
+    for (dialog_path, dialog) in dialog_files {
+        save_dialog_strings(&dialog_path, &IndexMapWrapper(dialog))?;
+    }
+    info!("Imported {imported} string(s), left {skipped} untranslated or fuzzy one(s) alone");
+    Ok(())
+}
+
+// Multi-line text gets one quoted line per line, the way gettext's own tools write it
+fn format_field(keyword: &str, value: &str) -> String {
+    let escape = |text: &str| {
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+    };
+    if !value.contains('\n') {
+        return format!("{keyword} \"{}\"\n", escape(value));
+    }
+    let mut field = format!("{keyword} \"\"\n");
+    for line in value.split_inclusive('\n') {
+        _ = writeln!(field, "\"{}\"", escape(line));
+    }
+    field
+}
+
+fn unquote(quoted: &str) -> Result<String, String> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| format!("Expected quoted text, but got {quoted}"))?;
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some(escaped @ ('"' | '\\')) => text.push(escaped),
+                other => return Err(format!("Bad escape \\{}", other.unwrap_or(' '))),
+            }
+        } else {
+            text.push(c);
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CatalogEntry, parse_catalog, write_catalog};
+
+    #[test]
+    fn catalogs_round_trip() {
+        let entries = [
+            CatalogEntry {
+                context: "EVENT.DAT/0012/01a4".to_owned(),
+                fuzzy: false,
+                msgid: "[Portrait86]こんにちは[More]\nいい天気だね！[End]".to_owned(),
+                msgstr: "[Portrait86]Hello there[More]\nNice weather![End]".to_owned(),
+                reference: "EVENT.DAT/0012.lz77.eventdialog.toml".to_owned(),
+            },
+            CatalogEntry {
+                context: "EVENT.DAT/0012/01c0".to_owned(),
+                fuzzy: true,
+                msgid: "「はい」\tと\\言った\n\n".to_owned(),
+                msgstr: "\"Yes,\"\tthey said \\ quietly\n\n".to_owned(),
+                reference: "EVENT.DAT/0012.lz77.eventdialog.toml".to_owned(),
+            },
+            CatalogEntry {
+                context: "EVENT.DAT/0013/0020".to_owned(),
+                msgid: "まだ[End]".to_owned(),
+                reference: "EVENT.DAT/0013.lz77.eventdialog.toml".to_owned(),
+                ..CatalogEntry::default()
+            },
+        ];
+        let parsed = parse_catalog(&write_catalog(&entries)).unwrap();
+        assert_eq!(parsed.len(), entries.len(), "entries went missing");
+        for (entry, read_back) in entries.iter().zip(&parsed) {
+            assert_eq!(read_back.context, entry.context, "context changed");
+            assert_eq!(read_back.fuzzy, entry.fuzzy, "fuzzy flag changed");
+            assert_eq!(read_back.msgid, entry.msgid, "msgid changed");
+            assert_eq!(read_back.msgstr, entry.msgstr, "msgstr changed");
+            assert_eq!(read_back.reference, entry.reference, "reference changed");
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{
+    events::{
+        Data, IndexMapWrapper, Pointer,
+        codec::{OrderedData, OrderedDialog, parse_events_keyed},
+        disasm::label,
+        load_dialog_strings, load_event_data, save_dialog_strings,
+    },
+    helpers::read_dat_blobs,
+};
+use core::iter;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Cursor, ErrorKind},
+    path::Path,
+};
+
+/// Records which scheme the keys in an unpacked `EVENT.DAT` folder follow.
+pub const KEYS_FILE: &str = "keys.toml";
+
+/// How pointer symbols, and with them dialog keys and listing labels, get picked when parsing an event.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyScheme {
+    // The original offset of whatever is pointed at, so finding one more pointer doesn't move any other key
+    Offset,
+    // Numbered in the order they turn up. Workspaces unpacked before keys.toml existed all use this.
+    Sequential,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeysFile {
+    scheme: KeyScheme,
+}
+
+/// Old symbols of an event mapped to new ones, from parsing the same event with both schemes.
+pub struct SymbolMap {
+    next_free: Pointer,
+    symbols: HashMap<Pointer, Pointer>,
+}
+
+impl KeyScheme {
+    pub const CURRENT: Self = Self::Offset;
+
+    /// Symbol for something at `offset`, when `assigned` symbols have been handed out so far.
+    pub fn symbol(self, offset: Pointer, assigned: usize) -> Pointer {
+        match self {
+            Self::Offset => offset,
+            Self::Sequential => Pointer::try_from(assigned).unwrap(),
+        }
+    }
+}
+
+impl SymbolMap {
+    fn get(&mut self, old: Pointer) -> Pointer {
+        // Anything added by hand since unpacking has no counterpart, so it gets moved out of the way
+        *self.symbols.entry(old).or_insert_with(|| {
+            let fresh = self.next_free;
+            self.next_free += 1;
+            fresh
+        })
+    }
+
+    /// Both parses have to be of the same event, so everything lines up item for item. Errors if they don't.
+    pub fn new(
+        old: &(OrderedData, OrderedDialog),
+        new: &(OrderedData, OrderedDialog),
+    ) -> Result<Self, io::Error> {
+        let all_symbols = |(ordered_data, dialog): &(OrderedData, OrderedDialog)| {
+            script_symbols(ordered_data)
+                .into_iter()
+                .chain(dialog.keys().copied())
+                .collect::<Vec<_>>()
+        };
+        let (old_symbols, new_symbols) = (all_symbols(old), all_symbols(new));
+        if old_symbols.len() != new_symbols.len() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "The two parses have {} and {} symbols, so they can't be matched up",
+                    old_symbols.len(),
+                    new_symbols.len()
+                ),
+            ));
+        }
+        Ok(Self {
+            next_free: new_symbols.iter().max().map_or(0, |max| max + 1),
+            symbols: old_symbols.into_iter().zip(new_symbols).collect(),
+        })
+    }
+
+    pub fn remap_data(&mut self, ordered_data: OrderedData) -> OrderedData {
+        ordered_data
+            .into_iter()
+            .map(|(section, data)| {
+                let remapped = data
+                    .into_iter()
+                    .map(|mut datum| {
+                        if let Some(pointer) = datum.get_pointer() {
+                            datum.set_pointer_symbol(self.get(pointer));
+                        }
+                        datum
+                    })
+                    .collect();
+                (self.get(section), remapped)
+            })
+            .collect()
+    }
+
+    pub fn remap_dialog(&mut self, dialog: OrderedDialog) -> OrderedDialog {
+        dialog
+            .into_iter()
+            .map(|(symbol, mut dialog_string)| {
+                for choice in &mut dialog_string.choices {
+                    choice.remap(|old| self.get(old));
+                }
+                (self.get(symbol), dialog_string)
+            })
+            .collect()
+    }
+
+    /// Rewrites every `L_xxxx` label in a listing or report.
+    pub fn remap_labels(&mut self, text: &str) -> String {
+        let mut remapped = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("L_") {
+            let (before, from_label) = rest.split_at(start);
+            remapped.push_str(before);
+            let hex_length = from_label
+                .get(2..)
+                .unwrap_or_default()
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(from_label.len() - 2);
+            let is_word_start = !before
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_');
+            let is_word_end = !from_label
+                .get(2 + hex_length..)
+                .and_then(|after| after.chars().next())
+                .is_some_and(|c| c.is_alphanumeric() || c == '_');
+            let symbol = from_label
+                .get(2..2 + hex_length)
+                .and_then(|hex| Pointer::from_str_radix(hex, 16).ok());
+            match symbol {
+                Some(old) if is_word_start && is_word_end => {
+                    remapped.push_str(&label(self.get(old)));
+                    rest = from_label.get(2 + hex_length..).unwrap_or_default();
+                }
+                _ => {
+                    remapped.push_str("L_");
+                    rest = from_label.get(2..).unwrap_or_default();
+                }
+            }
+        }
+        remapped.push_str(rest);
+        remapped
+    }
+}
+
+/// Every section and pointer of an event, in order. Parses of the same script line up symbol for symbol.
+pub fn script_symbols(ordered_data: &OrderedData) -> Vec<Pointer> {
+    ordered_data
+        .iter()
+        .flat_map(|(section, data)| {
+            iter::once(*section).chain(data.iter().filter_map(Data::get_pointer))
+        })
+        .collect()
+}
+
+/// Whether an unpacked eventdata.json still holds what parsing the event gives. Instructions without a pointer that
+/// older versions left as raw bytes count the same as those bytes, so JSON unpacked before the parser knew them matches.
+pub fn same_script(unpacked: &OrderedData, parsed: &OrderedData) -> bool {
+    as_raw(unpacked) == as_raw(parsed)
+}
+
+// Each section as JSON values, with every run of raw bytes and pointerless instructions merged into one raw chunk
+fn as_raw(ordered_data: &OrderedData) -> Vec<(Pointer, Vec<serde_json::Value>)> {
+    ordered_data
+        .iter()
+        .map(|(section, data)| {
+            let mut merged: Vec<Data> = Vec::with_capacity(data.len());
+            for datum in data {
+                let bytes = match datum {
+                    Data::Op(..) | Data::OpWord(..) | Data::Unmanaged(_) => {
+                        datum.clone().into_bytes()
+                    }
+                    _ => {
+                        merged.push(datum.clone());
+                        continue;
+                    }
+                };
+                if let Some(Data::Unmanaged(previous)) = merged.last_mut() {
+                    previous.extend(bytes);
+                } else {
+                    merged.push(Data::Unmanaged(bytes));
+                }
+            }
+            let values = merged
+                .iter()
+                .map(|datum| serde_json::to_value(datum).unwrap_or_default())
+                .collect();
+            (*section, values)
+        })
+        .collect()
+}
+
+/// Rewrites the keys of an unpacked EVENT.DAT folder to the current scheme, for the migrate-keys command. Needs the
+/// EVENT.DAT it was unpacked from.
+pub fn migrate(dat_dir: &Path, dat_path: &Path) -> Result<(), io::Error> {
+    let scheme = read_scheme(dat_dir)?;
+    if scheme == KeyScheme::CURRENT {
+        info!("{} already uses {scheme:?} keys", dat_dir.display());
+        return Ok(());
+    }
+    let file_names = fs::read_dir(dat_dir)?
+        .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Every event is checked before anything gets written, so a mismatch doesn't leave the folder half migrated
+    let mut migrations = Vec::new();
+    for (slot, data) in (0..).zip(read_dat_blobs(dat_path)?) {
+        let slot_prefix = format!("{slot:04}.");
+        let slot_files = file_names
+            .iter()
+            .filter(|name| {
+                name.starts_with(&slot_prefix)
+                    && (name.contains("eventdata")
+                        || name.contains("eventdialog")
+                        || Path::new(name)
+                            .extension()
+                            .is_some_and(|ext| ext.eq_ignore_ascii_case("evs")))
+            })
+            .collect::<Vec<_>>();
+        if slot_files.is_empty() {
+            continue;
+        }
+        let eof = u32::try_from(data.len()).unwrap();
+        let old = parse_events_keyed(&mut Cursor::new(&data), eof, scheme)?;
+        let new = parse_events_keyed(&mut Cursor::new(&data), eof, KeyScheme::CURRENT)?;
+        let mismatch = |problem: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{slot_prefix}{problem}. Was {} unpacked from {}?",
+                    dat_dir.display(),
+                    dat_path.display()
+                ),
+            )
+        };
+        let symbols = SymbolMap::new(&old, &new).map_err(|e| mismatch(format!(" {e}")))?;
+        if let Some(data_file) = slot_files
+            .iter()
+            .find(|name| name.ends_with("eventdata.json"))
+        {
+            // Symbols are matched up by position, which is only right if the JSON is still what the parse gives
+            let unpacked = load_event_data(dat_dir.join(data_file))?;
+            if !same_script(&unpacked, &old.0) {
+                return Err(mismatch(
+                    "eventdata.json doesn't match the event. Edits to it can't be matched up with the new keys, so \
+                     undo them, migrate, and redo them"
+                        .to_owned(),
+                ));
+            }
+        }
+        migrations.push((slot_files, symbols));
+    }
+    let migrated = migrations.len();
+    for (slot_files, mut symbols) in migrations {
+        for file_name in slot_files {
+            let path = dat_dir.join(file_name);
+            if file_name.ends_with("eventdata.json") {
+                let ordered_data = symbols.remap_data(load_event_data(&path)?);
+                fs::write(
+                    &path,
+                    serde_json::to_string(&IndexMapWrapper(ordered_data))?,
+                )?;
+            } else if file_name.ends_with("eventdialog.toml") {
+                let dialog = symbols.remap_dialog(load_dialog_strings(&path)?);
+                save_dialog_strings(&path, &IndexMapWrapper(dialog))?;
+            } else {
+                let text = fs::read_to_string(&path)?;
+                fs::write(&path, symbols.remap_labels(&text))?;
+            }
+        }
+    }
+    write_scheme(dat_dir, KeyScheme::CURRENT)?;
+    info!(
+        "Migrated {migrated} event(s) from {scheme:?} to {:?} keys",
+        KeyScheme::CURRENT
+    );
+    Ok(())
+}
+
+/// Which scheme an unpacked `EVENT.DAT` folder uses. Without a keys.toml, it's from before there was a choice.
+pub fn read_scheme(dat_dir: &Path) -> Result<KeyScheme, io::Error> {
+    match fs::read_to_string(dat_dir.join(KEYS_FILE)) {
+        Ok(text) => toml::from_str::<KeysFile>(&text)
+            .map(|keys_file| keys_file.scheme)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{KEYS_FILE}: {e}"))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(KeyScheme::Sequential),
+        Err(e) => Err(e),
+    }
+}
+
+/// Warns when an unpacked folder holding events has no keys.toml, as its keys are then taken to be sequential ones.
+pub fn warn_if_unrecorded(dat_dir: &Path) -> Result<(), io::Error> {
+    if dat_dir.join(KEYS_FILE).exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dat_dir)? {
+        let file_name = entry?.file_name().to_string_lossy().into_owned();
+        if file_name.ends_with("eventdata.json")
+            || file_name.ends_with("eventdialog.toml")
+            || Path::new(&file_name)
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("evs"))
+        {
+            warn!(
+                "{} has no {KEYS_FILE}, so its dialog keys and labels are taken to be sequential ones from before there \
+                 was a choice. Keys from catalogs or workspaces unpacked since won't line up with them. Run `aeroprism \
+                 migrate-keys` to update it.",
+                dat_dir.display()
+            );
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+pub fn write_scheme(dat_dir: &Path, scheme: KeyScheme) -> Result<(), io::Error> {
+    let text = toml::to_string(&KeysFile { scheme }).map_err(io::Error::other)?;
+    fs::write(
+        dat_dir.join(KEYS_FILE),
+        format!(
+            "# Written by aeroprism, and used by migrate-keys to tell whether dialog keys need updating\n{text}"
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyScheme, SymbolMap, same_script};
+    use crate::events::{Data, IndexMapWrapper, codec::parse_events_keyed};
+    use std::io::Cursor;
+
+    const EVENT: [u8; 0x20] = [
+        0x26, 0x00, 0x01, 0x00, // op_26
+        0x2a, 0x00, 0x02, 0x00, 0x05, 0x00, 0x00, 0x00, // op_2a
+        0x12, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, // text
+        0x0a, 0x00, 0x00, 0x00, // ret
+        0x82, 0xa0, 0x82, 0xa2, b'\\', 0x00, 0x00, 0x00, // the string
+    ];
+    // The event as unpacked before the parser knew op_26 and op_2a, with sequential keys
+    const BASELINE_JSON: &str = r#"{"0000":[{"Unmanaged":"260001002a00020005000000"},{"TxtPtr":1},"Ret"],"0001":[{"String":[]}]}"#;
+
+    #[test]
+    fn baseline_json_migrates() {
+        crate::ENGRISH.get_or_init(|| false);
+        let unpacked = serde_json::from_str::<IndexMapWrapper<Vec<Data>>>(BASELINE_JSON)
+            .unwrap()
+            .0;
+        let old = parse_events_keyed(&mut Cursor::new(EVENT), 0x20, KeyScheme::Sequential).unwrap();
+        let new = parse_events_keyed(&mut Cursor::new(EVENT), 0x20, KeyScheme::CURRENT).unwrap();
+        assert!(same_script(&unpacked, &old.0));
+
+        let migrated = SymbolMap::new(&old, &new).unwrap().remap_data(unpacked);
+        assert!(matches!(
+            migrated.get(&0x0000).and_then(|data| data.get(1)),
+            Some(Data::TxtPtr(0x18))
+        ));
+        assert!(migrated.contains_key(&0x18));
+    }
+
+    #[test]
+    fn edited_json_does_not_match() {
+        crate::ENGRISH.get_or_init(|| false);
+        let edited = BASELINE_JSON.replace("2a000200", "2a000300");
+        let unpacked = serde_json::from_str::<IndexMapWrapper<Vec<Data>>>(&edited)
+            .unwrap()
+            .0;
+        let (old, _) =
+            parse_events_keyed(&mut Cursor::new(EVENT), 0x20, KeyScheme::Sequential).unwrap();
+        assert!(!same_script(&unpacked, &old));
+    }
+}
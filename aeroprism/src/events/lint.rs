@@ -0,0 +1,233 @@
+use crate::events::{
+    Color, ControlCode, DialogItem, DialogString, dialog_files, load_dialog_strings,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, io, path::Path};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The fonts built into the binary, used unless others are given with `--fonts`.
+pub const DEFAULT_FONTS: &str = include_str!("../../fonts.toml");
+
+// See fonts.toml for what each of these means
+#[derive(Deserialize)]
+pub struct Font {
+    pub box_lines: usize,
+    pub box_width: u32,
+    pub default_width: u32,
+    #[serde(default)]
+    pub portrait_width: u32,
+    #[serde(default)]
+    pub value_width: u32,
+    #[serde(default)]
+    pub widths: HashMap<String, u32>,
+}
+
+/// One text box, as split up by [More], with everything placed in it.
+pub struct Page<'text> {
+    // How far in text starts, to make room for a portrait
+    pub indent: u32,
+    pub line_widths: Vec<u32>,
+    pub placed: Vec<Placed<'text>>,
+    pub portrait: Option<&'text str>,
+}
+
+/// Something taking up room on a line, at a spot in pixels from where text starts.
+pub struct Placed<'text> {
+    pub color: Color,
+    pub line: usize,
+    // The grapheme drawn, or None for anything without one, like raw bytes or a [Value] with no sample
+    pub text: Option<&'text str>,
+    pub width: u32,
+    pub x: u32,
+}
+
+// Where the next glyph goes while playing a string out, and in what color
+struct Pen {
+    color: Color,
+    line: usize,
+    x: u32,
+}
+
+impl Font {
+    /// Plays a string out the way the game shows it, and describes every line running off the box and every box
+    /// running out of lines.
+    pub fn check(&self, dialog_string: &DialogString) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (page_number, page) in (1..).zip(self.lay_out(dialog_string)) {
+            let limit = self.box_width.saturating_sub(page.indent);
+            // Only counted once something lands on the extra line, as strings often end in a newline
+            if page
+                .line_widths
+                .iter()
+                .skip(self.box_lines)
+                .any(|width| *width > 0)
+            {
+                problems.push(format!(
+                    "box {page_number} needs more than {} lines, so it wants another [More]",
+                    self.box_lines
+                ));
+            }
+            for (line_number, width) in (1..).zip(&page.line_widths) {
+                if *width > limit {
+                    problems.push(format!(
+                        "box {page_number} line {line_number} is {width} px wide, {} more than fits",
+                        width - limit
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Splits a string into boxes and places everything in them. `check` and the preview both go by this, so
+    /// whatever one reports running off the box, the other shows running off it.
+    pub fn lay_out<'text>(&'text self, dialog_string: &'text DialogString) -> Vec<Page<'text>> {
+        let mut pages = vec![Page::new(None, 0)];
+        let mut pen = Pen {
+            color: Color::White,
+            line: 0,
+            x: 0,
+        };
+        for item in &dialog_string.text {
+            let Some(page) = pages.last_mut() else {
+                break;
+            };
+            match item {
+                DialogItem::String(string) => {
+                    for grapheme in string.graphemes(true) {
+                        if grapheme == "\n" {
+                            pen.line += 1;
+                            pen.x = 0;
+                            page.line_widths.push(0);
+                        } else {
+                            page.place(&mut pen, Some(grapheme), self.width(grapheme));
+                        }
+                    }
+                }
+                DialogItem::ControlCode(ControlCode::More) => {
+                    // The portrait stays up from one box to the next
+                    let next_page = Page::new(page.portrait, page.indent);
+                    pages.push(next_page);
+                    (pen.line, pen.x) = (0, 0);
+                }
+                DialogItem::ControlCode(ControlCode::End) => break,
+                DialogItem::ControlCode(ControlCode::Value) => {
+                    page.place(&mut pen, None, self.value_width);
+                }
+                // [Push] waits for a button press, but carries on in the same box
+                DialogItem::ControlCode(
+                    ControlCode::None
+                    | ControlCode::Push
+                    | ControlCode::Select
+                    | ControlCode::Color
+                    | ControlCode::Portrait,
+                ) => {}
+                // Symbols from the Goldenboy release take up a glyph, and raw bytes are most likely glyphs the tables
+                // don't know about
+                DialogItem::ControlCode(_) | DialogItem::Raw(_) => {
+                    page.place(&mut pen, None, self.default_width);
+                }
+                DialogItem::Color(new_color) => pen.color = *new_color,
+                // Takes up the side of the box it shows in, and every box after
+                DialogItem::Portrait(portrait) => {
+                    page.portrait = Some(&portrait.0);
+                    page.indent = self.portrait_width;
+                }
+            }
+        }
+        pages
+    }
+
+    pub fn parse_table(text: &str) -> Result<HashMap<String, Self>, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn width(&self, grapheme: &str) -> u32 {
+        self.widths
+            .get(grapheme)
+            .copied()
+            .unwrap_or(self.default_width)
+    }
+}
+
+impl<'text> Page<'text> {
+    fn new(portrait: Option<&'text str>, indent: u32) -> Self {
+        Self {
+            indent,
+            line_widths: vec![0],
+            placed: Vec::new(),
+            portrait,
+        }
+    }
+
+    // Spaces only push the pen along
+    fn place(&mut self, pen: &mut Pen, text: Option<&'text str>, width: u32) {
+        if text.is_none_or(|grapheme| !grapheme.trim().is_empty()) {
+            self.placed.push(Placed {
+                color: pen.color,
+                line: pen.line,
+                text,
+                width,
+                x: pen.x,
+            });
+        }
+        pen.x += width;
+        if let Some(line_width) = self.line_widths.get_mut(pen.line) {
+            *line_width = pen.x;
+        }
+    }
+}
+
+/// Prints every dialog string in the workspace that doesn't fit the font's text box, and why.
+#[expect(clippy::print_stdout, reason = "the report is the output")]
+pub fn check_dialog(
+    workspace: &Path,
+    chosen_font: Option<String>,
+    fonts_path: Option<&Path>,
+) -> Result<(), io::Error> {
+    let (font_name, font) = load_font(chosen_font, fonts_path)?;
+    let (mut checked, mut flagged) = (0, 0);
+    for (dat_name, _, file_name) in dialog_files(workspace)? {
+        let dialog = load_dialog_strings(workspace.join(&dat_name).join(&file_name))?;
+        for (symbol, dialog_string) in dialog {
+            checked += 1;
+            let problems = font.check(&dialog_string);
+            if !problems.is_empty() {
+                flagged += 1;
+            }
+            for problem in problems {
+                println!("{dat_name}/{file_name} [{symbol:04x}]: {problem}");
+            }
+        }
+    }
+    println!("{flagged} of {checked} string(s) don't fit the {font_name} font's text box");
+    Ok(())
+}
+
+/// A font from the fonts table, the built in one unless another is given. Defaults to the one matching `-e`.
+pub fn load_font(
+    chosen_font: Option<String>,
+    fonts_path: Option<&Path>,
+) -> Result<(String, Font), io::Error> {
+    let fonts_text = match fonts_path {
+        Some(path) => fs::read_to_string(path)?,
+        None => DEFAULT_FONTS.to_owned(),
+    };
+    let mut fonts = Font::parse_table(&fonts_text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("fonts.toml: {e}")))?;
+    let font_name = chosen_font.unwrap_or_else(|| {
+        if *crate::ENGRISH.get().unwrap() {
+            "en"
+        } else {
+            "ja"
+        }
+        .to_owned()
+    });
+    let font = fonts.remove(&font_name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("No font named {font_name} in fonts.toml"),
+        )
+    })?;
+    Ok((font_name, font))
+}
@@ -1,10 +1,33 @@
+pub mod asm;
+pub mod bilingual;
+pub mod cfg;
+pub mod charmap;
 pub mod codec;
+pub mod disasm;
+pub mod gettext;
+pub mod keys;
+pub mod lint;
+pub mod opcodes;
+pub mod pointers;
+pub mod prompts;
+pub mod reachability;
+pub mod release;
+pub mod relocate;
 pub mod sjis_map;
+pub mod textbox;
+pub mod tm;
+pub mod validate;
 extern crate alloc;
 use crate::{
     events::{
+        asm::assemble,
         codec::{DialogMap, OrderedData, OrderedDialog, marshal_events},
+        keys::{KeyScheme, read_scheme, warn_if_unrecorded},
+        opcodes::by_code,
+        prompts::Choice,
+        relocate::RelocationReport,
         sjis_map::utf8_to_ps2,
+        validate::find_unencodable,
     },
     helpers::{decode_hex, encode_hex},
 };
@@ -12,16 +35,18 @@ use alloc::{
     collections::{BTreeMap, BTreeSet},
     rc::Rc,
 };
-use core::{cell::RefCell, fmt, fmt::Display, mem, str::FromStr};
+use core::{cell::RefCell, fmt, fmt::Display, iter, mem, str::FromStr};
 use indexmap::IndexMap;
-use log::{debug, error, trace};
+use log::{debug, error, trace, warn};
 use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
     de::{self, DeserializeOwned, Error, Visitor},
     ser::SerializeSeq,
 };
 use std::{
-    fs::OpenOptions,
+    collections::HashSet,
+    ffi::OsStr,
+    fs::{self, OpenOptions},
     io::{self, BufReader, BufWriter, Read, Write},
     path::{Path, PathBuf},
 };
@@ -32,36 +57,92 @@ const GUESTIMATED_LENGTH: usize = 256;
 type Pointer = u32;
 type Offset = u32;
 
-#[repr(u8)]
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all(deserialize = "lowercase"))]
-enum ControlCode {
+#[expect(
+    clippy::arbitrary_source_item_ordering,
+    reason = "Shared codes first, then the symbols"
+)]
+pub enum ControlCode {
     None,
     #[serde(alias = "wait")]
-    Push = b'%',
-    End = b'\\',
+    Push,
+    End,
     #[serde(alias = "clear")]
-    More = b'?',
-    Select = b'*',
-    Value = b'$',
-    Important = b'J', // Goldenboy release specific
-    Musik = b'v',     // Goldenboy release specific
-    Sword = b'V',     // Goldenboy release specific
-    Cross = b'|',     // Goldenboy release specific
-    Triangle = 0x7F,  // <delete> // Goldenboy release specific
-    Square = b'~',    // Goldenboy release specific
-    Circle = b'}',    // Goldenboy release specific
-    Claw = b'Z',      // Goldenboy release specific
-    Star = b'M',      // Goldenboy release specific
-    Sol = b'L',       // Goldenboy release specific
-    Crown = b'k',     // Goldenboy release specific
-    Helmet = b'i',    // Goldenboy release specific
-    Fluid = b'H',     // Goldenboy release specific
-    Moon = b'N',      // Goldenboy release specific
-    Hat = b'h',       // Goldenboy release specific
+    More,
+    Select,
+    Value,
+    // Symbols, which only some releases have. Which byte each one is depends on the release, see releases.toml.
+    Important,
+    Musik,
+    Sword,
+    Cross,
+    Triangle,
+    Square,
+    Circle,
+    Claw,
+    Star,
+    Sol,
+    Crown,
+    Helmet,
+    Fluid,
+    Moon,
+    Hat,
     // Newline = b'@',
-    Color = b'c',
-    Portrait = b'#',
+    Color,
+    Portrait,
+}
+
+impl ControlCode {
+    // The byte a code is stored as in the release being worked on
+    fn byte(self) -> Option<u8> {
+        match self {
+            Self::None => None,
+            Self::Push => Some(b'%'),
+            Self::End => Some(b'\\'),
+            Self::More => Some(b'?'),
+            Self::Select => Some(b'*'),
+            Self::Value => Some(b'$'),
+            Self::Color => Some(b'c'),
+            Self::Portrait => Some(b'#'),
+            symbol => crate::RELEASE.get()?.symbol_byte(symbol),
+        }
+    }
+
+    // The codes every release has
+    const fn from_shared(byte: u8) -> Option<Self> {
+        match byte {
+            b'%' => Some(Self::Push),
+            b'\\' => Some(Self::End),
+            b'?' => Some(Self::More),
+            b'*' => Some(Self::Select),
+            b'$' => Some(Self::Value),
+            b'c' => Some(Self::Color),
+            b'#' => Some(Self::Portrait),
+            _ => None,
+        }
+    }
+
+    fn symbol_from_str(name: &str) -> Option<Self> {
+        match name {
+            "important" => Some(Self::Important),
+            "musik" => Some(Self::Musik),
+            "sword" => Some(Self::Sword),
+            "cross" => Some(Self::Cross),
+            "triangle" => Some(Self::Triangle),
+            "square" => Some(Self::Square),
+            "circle" => Some(Self::Circle),
+            "claw" => Some(Self::Claw),
+            "star" => Some(Self::Star),
+            "sol" => Some(Self::Sol),
+            "crown" => Some(Self::Crown),
+            "helmet" => Some(Self::Helmet),
+            "fluid" => Some(Self::Fluid),
+            "moon" => Some(Self::Moon),
+            "hat" => Some(Self::Hat),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for ControlCode {
@@ -74,24 +155,15 @@ impl FromStr for ControlCode {
             "more" => Ok(Self::More),
             "select" => Ok(Self::Select),
             "value" => Ok(Self::Value),
-            "important" => Ok(Self::Important),
-            "musik" => Ok(Self::Musik),
-            "sword" => Ok(Self::Sword),
-            "cross" => Ok(Self::Cross),
-            "triangle" => Ok(Self::Triangle),
-            "square" => Ok(Self::Square),
-            "circle" => Ok(Self::Circle),
-            "claw" => Ok(Self::Claw),
-            "star" => Ok(Self::Star),
-            "sol" => Ok(Self::Sol),
-            "crown" => Ok(Self::Crown),
-            "helmet" => Ok(Self::Helmet),
-            "fluid" => Ok(Self::Fluid),
-            "moon" => Ok(Self::Moon),
-            "hat" => Ok(Self::Hat),
             "color" => Ok(Self::Color),
             "portrait" => Ok(Self::Portrait),
-            other => Err(format!("Invalid ControlCode variant: {other}")),
+            other => match Self::symbol_from_str(other) {
+                Some(symbol) if symbol.byte().is_some() => Ok(symbol),
+                Some(_) => Err(format!(
+                    "{other} isn't a symbol in this release (see --release)"
+                )),
+                None => Err(format!("Invalid ControlCode variant: {other}")),
+            },
         }
     }
 }
@@ -128,31 +200,9 @@ impl Display for ControlCode {
 
 impl From<u8> for ControlCode {
     fn from(value: u8) -> Self {
-        match value {
-            b'%' => Self::Push,
-            b'\\' => Self::End,
-            b'?' => Self::More,
-            b'*' => Self::Select,
-            b'$' => Self::Value,
-            b'J' => Self::Important,
-            b'v' => Self::Musik,
-            b'V' => Self::Sword,
-            b'|' => Self::Cross,
-            0x7F => Self::Triangle,
-            b'~' => Self::Square,
-            b'}' => Self::Circle,
-            b'Z' => Self::Claw,
-            b'M' => Self::Star,
-            b'L' => Self::Sol,
-            b'k' => Self::Crown,
-            b'i' => Self::Helmet,
-            b'H' => Self::Fluid,
-            b'N' => Self::Moon,
-            b'h' => Self::Hat,
-            b'c' => Self::Color,
-            b'#' => Self::Portrait,
-            _ => Self::None,
-        }
+        Self::from_shared(value)
+            .or_else(|| crate::RELEASE.get()?.symbol(value))
+            .unwrap_or(Self::None)
     }
 }
 
@@ -167,7 +217,7 @@ impl Display for Portrait {
 
 #[repr(u8)]
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
-enum Color {
+pub enum Color {
     Blue = b'1',
     Red = b'2',
     Purple = b'3',
@@ -208,22 +258,19 @@ impl Display for Color {
     }
 }
 
-impl From<u8> for Color {
-    fn from(value: u8) -> Self {
+impl TryFrom<u8> for Color {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            b'1' => Self::Blue,
-            b'2' => Self::Red,
-            b'3' => Self::Purple,
-            b'4' => Self::Green,
-            b'5' => Self::Cyan,
-            b'6' => Self::Yellow,
-            b'7' => Self::White,
-            other => {
-                error!(
-                    "Color parse error: Expected numerals 1-7, but got {other:02x}. I'll give you white instead."
-                );
-                Self::White
-            }
+            b'1' => Ok(Self::Blue),
+            b'2' => Ok(Self::Red),
+            b'3' => Ok(Self::Purple),
+            b'4' => Ok(Self::Green),
+            b'5' => Ok(Self::Cyan),
+            b'6' => Ok(Self::Yellow),
+            b'7' => Ok(Self::White),
+            other => Err(other),
         }
     }
 }
@@ -233,21 +280,41 @@ enum DialogItem {
     Color(Color),
     ControlCode(ControlCode),
     Portrait(Portrait),
+    // Bytes with no known meaning, written as [Raw 81 ff] and put back exactly as they were
+    Raw(Vec<u8>),
     String(String),
 }
 
 impl DialogItem {
     fn into_bytes(self) -> Vec<u8> {
         match self {
-            Self::ControlCode(cc) => vec![cc as u8],
-            Self::Color(color) => vec![ControlCode::Color as u8, color as u8],
-            Self::Portrait(portrait) => {
-                [vec![ControlCode::Portrait as u8], portrait.0.into_bytes()].concat()
-            }
+            Self::ControlCode(cc) => cc.byte().into_iter().collect(),
+            Self::Color(color) => vec![b'c', color as u8],
+            Self::Portrait(portrait) => [vec![b'#'], portrait.0.into_bytes()].concat(),
+            Self::Raw(bytes) => bytes,
             Self::String(string) => {
                 let mut bytes = Vec::with_capacity(string.len() * 2);
-                for g in string.graphemes(true) {
-                    bytes.extend(utf8_to_ps2(g).unwrap());
+                let mut graphemes = string.grapheme_indices(true).peekable();
+                while let Some((start, g)) = graphemes.next() {
+                    // Custom entries can cover several characters, like a ligature, but never only part of one
+                    let custom = crate::CHARMAP.get().and_then(|charmap| {
+                        let rest = string.get(start..)?;
+                        let (length, encoded) = charmap.encode(rest)?;
+                        rest.grapheme_indices(true)
+                            .map(|(index, _)| index)
+                            .chain(iter::once(rest.len()))
+                            .any(|boundary| boundary == length)
+                            .then_some((length, encoded))
+                    });
+                    if let Some((length, encoded)) = custom {
+                        bytes.extend(encoded);
+                        while graphemes
+                            .next_if(|(next, _)| *next < start + length)
+                            .is_some()
+                        {}
+                    } else {
+                        bytes.extend(utf8_to_ps2(g).unwrap());
+                    }
                 }
                 bytes
             }
@@ -261,6 +328,14 @@ impl Display for DialogItem {
             Self::ControlCode(control_code) => write!(f, "{control_code}"),
             Self::Color(color) => write!(f, "{color}"),
             Self::Portrait(portrait) => write!(f, "{portrait}"),
+            Self::Raw(bytes) => {
+                let hex = bytes
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                write!(f, "[Raw {hex}]")
+            }
             Self::String(string) => write!(f, "{string}"),
         }
     }
@@ -268,6 +343,9 @@ impl Display for DialogItem {
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct DialogString {
+    // The options of a [Select] prompt, noted down when unpacking
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    choices: Vec<Choice>,
     #[serde(default, skip_serializing_if = "is_false")]
     padded: bool,
     #[serde(
@@ -284,6 +362,11 @@ pub enum Data {
     J(u8, Pointer),
     Jal(u8, u8, u8, u32, u32, Pointer),
     Multi(u8, Pointer, Vec<u32>),
+    // Opcodes without any pointers
+    Op(u8, u8),
+    // An opcode whose word points somewhere in the event, either by its Shape::Ptr or a label written in a listing
+    OpPtr(u8, u8, Pointer),
+    OpWord(u8, u8, u32),
     // Just a solo pointer. Carries no opcode.
     Ptr(Pointer),
     Ret,
@@ -303,9 +386,12 @@ impl Data {
             | Self::TxtPtr(pointer)
             | Self::Cop(_, _, _, pointer)
             | Self::Cop2(_, _, _, pointer)
+            | Self::OpPtr(_, _, pointer)
             // For those ops with two pointers, this takes the place of that other pointer
             | Self::Ptr(pointer) => Some(*pointer),
-            Self::Ret | Self::String(_) | Self::Unmanaged(_) => None,
+            Self::Op(..) | Self::OpWord(..) | Self::Ret | Self::String(_) | Self::Unmanaged(_) => {
+                None
+            }
         }
     }
 
@@ -346,14 +432,22 @@ impl Data {
                 pointer.to_le_bytes(),
             ]
             .concat(),
+            Self::Op(op, c) => vec![op, 0x00, c, 0x00],
+            Self::OpPtr(op, c, pointer) => [[op, 0x00, c, 0x00], pointer.to_le_bytes()].concat(),
+            Self::OpWord(op, c, field) => [[op, 0x00, c, 0x00], field.to_le_bytes()].concat(),
             Self::Ptr(pointer) => pointer.to_le_bytes().to_vec(),
             Self::Unmanaged(bytes) => bytes,
         }
     }
 
+    // Whether the script never carries on past this into whatever comes next
+    const fn is_end(&self) -> bool {
+        matches!(self, Self::Ret | Self::J(0x0b, _))
+    }
+
     fn len(&self) -> usize {
         match self {
-            Self::Ret => 4,
+            Self::Ret | Self::Op(..) => 4,
             Self::J(_op, pointer) => 4 + size_of_val(pointer),
             Self::Jal(_op, _c, _d, field_1, field_2, pointer) => {
                 4 + size_of_val(field_1) + size_of_val(field_2) + size_of_val(pointer)
@@ -363,11 +457,29 @@ impl Data {
             Self::String(string) => string.borrow().len(),
             Self::Cop(_op, _c, _d, pointer) => 4 + size_of_val(pointer),
             Self::Cop2(_op, _c, field, pointer) => 4 + size_of_val(field) + size_of_val(pointer),
+            Self::OpPtr(_op, _c, pointer) => 4 + size_of_val(pointer),
+            Self::OpWord(_op, _c, field) => 4 + size_of_val(field),
             Self::Ptr(pointer) => size_of_val(pointer),
             Self::Unmanaged(bytes) => bytes.len(),
         }
     }
 
+    const fn opcode(&self) -> Option<u8> {
+        match self {
+            Self::Ret => Some(0x0a),
+            Self::TxtPtr(_) => Some(0x12),
+            Self::J(op, _)
+            | Self::Jal(op, _, _, _, _, _)
+            | Self::Multi(op, _, _)
+            | Self::Cop(op, _, _, _)
+            | Self::Cop2(op, _, _, _)
+            | Self::Op(op, _)
+            | Self::OpPtr(op, _, _)
+            | Self::OpWord(op, _, _) => Some(*op),
+            Self::Ptr(_) | Self::String(_) | Self::Unmanaged(_) => None,
+        }
+    }
+
     const fn set_pointer_symbol(&mut self, symbol: Pointer) {
         // Replace the actual pointer value with a symbol that won't change no matter how the pointers are moved or mutated
         // This allows dialog files to be reused seamlessly across different translations without the user having to muck with offsets manually.
@@ -378,8 +490,9 @@ impl Data {
             | Self::TxtPtr(pointer)
             | Self::Cop(_, _, _, pointer)
             | Self::Cop2(_, _, _, pointer)
+            | Self::OpPtr(_, _, pointer)
             | Self::Ptr(pointer) => *pointer = symbol,
-            Self::Ret | Self::String(_) | Self::Unmanaged(_) => {}
+            Self::Op(..) | Self::OpWord(..) | Self::Ret | Self::String(_) | Self::Unmanaged(_) => {}
         }
     }
 }
@@ -424,6 +537,15 @@ impl Display for Data {
                     op_to_str(*op)
                 )?;
             }
+            Self::Op(op, c) => {
+                write!(f, "{} {c:02x}", op_to_str(*op))?;
+            }
+            Self::OpPtr(op, c, pointer) => {
+                write!(f, "{} {c:02x} -> ({pointer:04x})", op_to_str(*op))?;
+            }
+            Self::OpWord(op, c, field) => {
+                write!(f, "{} {c:02x} {field:04x}", op_to_str(*op))?;
+            }
             Self::Ptr(pointer) => {
                 write!(f, "Ptr -> ({pointer:04x})")?;
             }
@@ -538,7 +660,11 @@ impl DataItems {
         self.data_items.insert(data_offset, data);
     }
 
-    fn into_ordered_data(self, mut dialog_data: DialogMap) -> (OrderedData, OrderedDialog) {
+    fn into_ordered_data(
+        self,
+        mut dialog_data: DialogMap,
+        keys: KeyScheme,
+    ) -> (OrderedData, OrderedDialog) {
         let mut ordered_data = IndexMap::with_capacity(self.data_items.len());
         let mut ordered_dialog = IndexMap::with_capacity(dialog_data.len());
         let mut data_items_iter = self.data_items.into_iter();
@@ -550,21 +676,21 @@ impl DataItems {
         for (offset, mut data) in data_items_iter.by_ref() {
             // If the current data item has a pointer, convert it into a symbol
             if let Some(data_pointer) = data.get_pointer() {
-                let mut symbol = Pointer::try_from(pointer_symbols.len()).unwrap();
-                symbol = *pointer_symbols.entry(data_pointer).or_insert(symbol);
+                let new_symbol = keys.symbol(data_pointer, pointer_symbols.len());
+                let symbol = *pointer_symbols.entry(data_pointer).or_insert(new_symbol);
                 data.set_pointer_symbol(symbol);
             }
             // If the current offset is referred to by a pointer, create a symbol for it
             if let Some(pointer) = self.pointer_tracker.get(&offset) {
-                let symbol = Pointer::try_from(pointer_symbols.len()).unwrap();
-                pointer_symbols.entry(*pointer).or_insert(symbol);
+                let new_symbol = keys.symbol(*pointer, pointer_symbols.len());
+                pointer_symbols.entry(*pointer).or_insert(new_symbol);
             }
             if let Some(symbol) = pointer_symbols.get(&offset) {
                 current_section = *symbol;
             }
             if let Some(dialog_item) = dialog_data.remove(&offset) {
-                let mut symbol = Pointer::try_from(pointer_symbols.len()).unwrap();
-                symbol = *pointer_symbols.entry(offset).or_insert(symbol);
+                let new_symbol = keys.symbol(offset, pointer_symbols.len());
+                let symbol = *pointer_symbols.entry(offset).or_insert(new_symbol);
                 ordered_dialog.insert(symbol, dialog_item);
             }
             let data_set = ordered_data
@@ -586,6 +712,12 @@ impl DataItems {
         let mut next_item = None;
         if let Some((offset, data)) = self.data_items.range_mut(..pointer).next_back() {
             trace!("Lookback to {pointer:04x} => {data}");
+            // Something points into the middle of this, so it was never really an instruction
+            if matches!(data, Data::Op(..) | Data::OpPtr(..) | Data::OpWord(..))
+                && pointer < offset + Offset::try_from(data.len()).unwrap()
+            {
+                *data = Data::Unmanaged(mem::replace(data, Data::Ret).into_bytes());
+            }
             if let Data::Unmanaged(bytes) = data {
                 next_item = Some(Data::Unmanaged(
                     bytes.split_off((pointer - offset) as usize),
@@ -623,23 +755,13 @@ enum BytesOrPointer {
 
 #[inline]
 fn op_to_str(op: u8) -> &'static str {
-    match op {
-        0x0b => "J",
-        0x38 => "XORI",
-        0x1e => "BGTZ",
-        0x17 => "BNE",
-        0x41 => "COP",
-        0x4a => "COP2",
-        0x33 => "ANDI",
-        0x00 => "SLL",
-        0x24 | 0x25 => "ADDIU",
-        0x0c | 0x0f => "JAL",
-        0x10 => "BEQ",
-        _ => {
+    by_code(op).map_or_else(
+        || {
             error!("Bad opcode {op:02x}");
             "Bad Opcode!"
-        }
-    }
+        },
+        |opcode| opcode.mnemonic,
+    )
 }
 
 #[expect(clippy::indexing_slicing, reason = "the checks are sufficient")]
@@ -676,6 +798,17 @@ fn parse_dialog(input: &str) -> Result<Vec<DialogItem>, String> {
             // Parse the tag name into an actual tag object
             if let Some(num) = inner.strip_prefix("portrait") {
                 out.push(DialogItem::Portrait(Portrait(num.trim().to_owned())));
+            } else if let Some(hex) = inner.strip_prefix("raw") {
+                let bytes = hex
+                    .split_whitespace()
+                    .map(|byte| u8::from_str_radix(byte, 16))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()
+                    .filter(|bytes| !bytes.is_empty())
+                    .ok_or_else(|| {
+                        format!("Expected hex bytes like [Raw 81 ff], but got [{inner}]")
+                    })?;
+                out.push(DialogItem::Raw(bytes));
             } else {
                 // Try Color first
                 if let Ok(col) = Color::from_str(&inner) {
@@ -684,6 +817,8 @@ fn parse_dialog(input: &str) -> Result<Vec<DialogItem>, String> {
                 // Then ControlCode
                 else if let Ok(cc) = ControlCode::from_str(&inner) {
                     out.push(DialogItem::ControlCode(cc));
+                } else if ControlCode::symbol_from_str(&inner).is_some() {
+                    return Err(format!("The release has no {inner} symbol (see --release)"));
                 } else {
                     return Err(format!("Unknown tag: {inner}"));
                 }
@@ -825,53 +960,258 @@ pub fn save_dialog_strings(
     Ok(())
 }
 
-// pub(crate) fn save_event_data(
-//     path: &PathBuf,
-//     events: &IndexMapWrapper<Vec<Data>>,
-// ) -> Result<(), io::Error> {
-//     let strings = serde_json::to_string(&events).unwrap();
-
-//     let f = OpenOptions::new()
-//         .create(true)
-//         .truncate(true)
-//         .write(true)
-//         .open(path)?;
-//     let mut f = BufWriter::new(f);
-//     f.write_all(strings.as_bytes())?;
-//     Ok(())
-// }
+pub fn load_event_data<P: AsRef<Path>>(path: P) -> Result<OrderedData, io::Error> {
+    let file = OpenOptions::new().read(true).open(path)?;
+    let mut string =
+        String::with_capacity(usize::try_from(file.metadata().unwrap().len()).unwrap());
+    let mut br = BufReader::new(file);
+    br.read_to_string(&mut string)?;
+    Ok(serde_json::from_str::<IndexMapWrapper<Vec<Data>>>(&string)?.0)
+}
 
-// pub(crate) fn load_event_data(path: &PathBuf) -> Result<OrderedData, io::Error> {
-//     let f = OpenOptions::new().read(true).open(path)?;
-//     let mut string = String::with_capacity(f.metadata().unwrap().len() as usize);
-//     let mut f = BufReader::new(f);
-//     f.read_to_string(&mut string)?;
-//     Ok(serde_json::from_str::<IndexMapWrapper<Vec<Data>>>(&string)?.0)
-// }
+/// Finds the event data (or the listing used in place of it) and dialog files for a slot like `EVENT.DAT/0012`,
+/// whatever extensions they were unpacked with. The dialog file may not exist, as events without any dialog don't get
+/// one.
+pub fn find_event_files(slot: &Path) -> Result<(PathBuf, PathBuf), io::Error> {
+    let slot_name = slot.file_name().unwrap_or_default().to_string_lossy();
+    let slot_number = slot_name.split('.').next().unwrap_or_default();
+    let dat_dir = slot.parent().unwrap_or_else(|| Path::new("."));
+    let mut found = Vec::with_capacity(1);
+    for entry in fs::read_dir(dat_dir)? {
+        let file_name = entry?.file_name().to_string_lossy().into_owned();
+        if !file_name.starts_with(format!("{slot_number}.").as_str()) {
+            continue;
+        }
+        if let Some(stem) = file_name
+            .strip_suffix("eventdata.json")
+            .or_else(|| file_name.strip_suffix("evs"))
+        {
+            found.push((
+                dat_dir.join(&file_name),
+                dat_dir.join(format!("{stem}eventdialog.toml")),
+            ));
+        }
+    }
+    match found.len() {
+        0 => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No event data found for {}", slot.display()),
+        )),
+        1 => Ok(found.swap_remove(0)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} has both an eventdata.json and a listing. Delete one of them.",
+                slot.display()
+            ),
+        )),
+    }
+}
+
+/// Loads the script and dialog of an event, assembling the script when the slot has a listing in place of its JSON.
+pub fn load_event_files(
+    data_path: &Path,
+    dialog_path: &Path,
+) -> Result<(OrderedData, Option<OrderedDialog>), io::Error> {
+    let dialog = if dialog_path.exists() {
+        Some(load_dialog_strings(dialog_path)?)
+    } else {
+        None
+    };
+    if data_path.extension().is_none_or(|ext| ext != "evs") {
+        return Ok((load_event_data(data_path)?, dialog));
+    }
+    let (ordered_data, dialog_items) =
+        assemble(&fs::read_to_string(data_path)?, dialog.unwrap_or_default()).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", data_path.display()),
+            )
+        })?;
+    Ok((ordered_data, Some(dialog_items)))
+}
 
+/// Every dialog file in a workspace as (DAT name, slot, file name), in order.
+pub fn dialog_files(workspace: &Path) -> Result<Vec<(String, String, String)>, io::Error> {
+    let read_names = |dir: &Path| {
+        let mut names = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| (e.file_name().to_string_lossy().into_owned(), e.path())))
+            .collect::<Result<Vec<_>, io::Error>>()?;
+        names.sort();
+        Ok::<_, io::Error>(names)
+    };
+    let mut files = Vec::new();
+    for (dat_name, dat_dir) in read_names(workspace)? {
+        if !dat_dir.is_dir() || !dat_name.to_ascii_uppercase().ends_with(".DAT") {
+            continue;
+        }
+        warn_if_unrecorded(&dat_dir)?;
+        for (file_name, _) in read_names(&dat_dir)? {
+            if file_name.ends_with("eventdialog.toml") {
+                let slot = file_name.split('.').next().unwrap_or_default().to_owned();
+                files.push((dat_name.clone(), slot, file_name));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Loads an unpacked event and its dialog, if it has any. The title is the event's path from the workspace, like
+/// `EVENT.DAT/0012.lz77.eventdata.json`.
+pub fn load_event(event: &Path) -> Result<(String, OrderedData, Option<OrderedDialog>), io::Error> {
+    let (data_path, dialog_path) = find_event_files(event)?;
+    let (ordered_data, dialog) = load_event_files(&data_path, &dialog_path)?;
+    let workspace = data_path.parent().and_then(Path::parent).unwrap_or(event);
+    let title = data_path
+        .strip_prefix(workspace)
+        .unwrap_or(&data_path)
+        .to_string_lossy()
+        .into_owned();
+    Ok((title, ordered_data, dialog))
+}
+
+/// Writes a command's output to a file, or the console without one.
+#[expect(
+    clippy::print_stdout,
+    reason = "the output goes to the console unless asked otherwise"
+)]
+pub fn print_or_write(output: &str, out_path: Option<&Path>) -> Result<(), io::Error> {
+    if let Some(path) = out_path {
+        fs::write(path, output)?;
+    } else {
+        print!("{output}");
+    }
+    Ok(())
+}
+
+/// Rebuilds an event from its JSON and dialog, along with where its sections moved to.
 pub fn rebuild_event<P: AsRef<Path>>(
     data: &[u8],
     file_name: &str,
     dialog_file_path: P,
-) -> Result<Vec<u8>, io::Error> {
+) -> Result<(Vec<u8>, RelocationReport), io::Error> {
     let ordered_data = serde_json::from_slice::<IndexMapWrapper<Vec<Data>>>(data)?.0;
-    #[expect(
-        clippy::if_then_some_else_none,
-        reason = "Closure would require unwrapping"
-    )]
     let dialog_items = if dialog_file_path.as_ref().exists() {
-        Some(load_dialog_strings(dialog_file_path.as_ref())?)
+        let dialog = load_dialog_strings(dialog_file_path.as_ref())?;
+        check_encodable(&dialog, dialog_file_path.as_ref())?;
+        Some(dialog)
     } else {
         None
     };
 
-    let event_data = marshal_events(
+    Ok(marshal_events(
         // &data,
         ordered_data,
         dialog_items,
         file_name,
+        read_scheme(dat_dir(dialog_file_path.as_ref()))?,
+    ))
+}
+
+/// Same as `rebuild_event`, but from an event listing rather than the JSON.
+pub fn rebuild_event_listing<P: AsRef<Path>>(
+    data: &[u8],
+    file_name: &str,
+    dialog_file_path: P,
+) -> Result<(Vec<u8>, RelocationReport), io::Error> {
+    let listing = String::from_utf8(data.to_vec()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{file_name} isn't valid UTF-8: {e}"),
+        )
+    })?;
+    let existing_dialog = if dialog_file_path.as_ref().exists() {
+        let dialog = load_dialog_strings(dialog_file_path.as_ref())?;
+        check_encodable(&dialog, dialog_file_path.as_ref())?;
+        dialog
+    } else {
+        OrderedDialog::new()
+    };
+    let (ordered_data, dialog_items) = assemble(&listing, existing_dialog)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{file_name}: {e}")))?;
+    Ok(marshal_events(
+        ordered_data,
+        Some(dialog_items),
+        file_name,
+        read_scheme(dat_dir(dialog_file_path.as_ref()))?,
+    ))
+}
+
+/// Rebuilds an unpacked event, finding its dialog file from the event data or listing's name.
+pub fn rebuild_slot(
+    component_file: &Path,
+    data: &[u8],
+) -> Result<(Vec<u8>, RelocationReport), io::Error> {
+    let dialog_file_stem = component_file.file_stem().unwrap();
+    let mut dialog_file_path = component_file.parent().unwrap().join(dialog_file_stem);
+    dialog_file_path.add_extension("eventdialog");
+    dialog_file_path.add_extension("toml");
+    let file_name = component_file.to_string_lossy();
+    if component_file.extension() == Some(OsStr::new("evs")) {
+        rebuild_event_listing(data, &file_name, dialog_file_path)
+    } else {
+        rebuild_event(data, &file_name, dialog_file_path)
+    }
+}
+
+/// Logs what moved while rebuilding an event, and what got left pointing at the old spots. With `strict`, anything
+/// left pointing at the wrong place fails the build instead, and so does anything that might be.
+pub fn check_relocations(
+    report: &RelocationReport,
+    file_name: &str,
+    strict: bool,
+) -> Result<(), io::Error> {
+    for (symbol, offset) in &report.moves {
+        debug!("{file_name}: {symbol:04x} moved to {offset:04x}");
+    }
+    if !strict {
+        for ambiguous_reference in &report.ambiguous_references {
+            warn!("{file_name}: {ambiguous_reference}, unless it's meant for what's there now");
+        }
+    }
+    if report.is_clean() && (!strict || report.ambiguous_references.is_empty()) {
+        return Ok(());
+    }
+    let mut message =
+        format!("{file_name} has references that weren't updated. See `aeroprism relocations`.");
+    for invalid_pointer in &report.invalid_pointers {
+        message.push_str(format!("\n  {invalid_pointer}").as_str());
+    }
+    for stale_reference in &report.stale_references {
+        message.push_str(format!("\n  {stale_reference}").as_str());
+    }
+    if strict {
+        for ambiguous_reference in &report.ambiguous_references {
+            message.push_str(
+                format!("\n  {ambiguous_reference}, unless it's meant for what's there now")
+                    .as_str(),
+            );
+        }
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    error!("{message}");
+    Ok(())
+}
+
+// Slots sit right in their DAT folder, next to keys.toml
+fn dat_dir(dialog_file_path: &Path) -> &Path {
+    dialog_file_path.parent().unwrap_or_else(|| Path::new("."))
+}
+
+// Fails listing every character that has no encoding, rather than panicking partway through encoding them
+fn check_encodable(dialog: &OrderedDialog, dialog_file_path: &Path) -> Result<(), io::Error> {
+    let unencodable = find_unencodable(dialog, false);
+    if unencodable.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!(
+        "{} has text that can't be encoded. Try `aeroprism validate --fix`.",
+        dialog_file_path.display()
     );
-    Ok(event_data)
+    for character in unencodable {
+        message.push_str(format!("\n  {character}").as_str());
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, message))
 }
 
 pub fn load_dialog_strings<P: AsRef<Path>>(path: P) -> Result<OrderedDialog, io::Error> {
@@ -885,6 +1225,42 @@ pub fn load_dialog_strings<P: AsRef<Path>>(path: P) -> Result<OrderedDialog, io:
         .0)
 }
 
+// Sections holding dialog rather than script
+fn string_sections(ordered_data: &OrderedData) -> HashSet<Pointer> {
+    ordered_data
+        .iter()
+        .filter(|(_, data)| matches!(data.first(), Some(Data::String(_))))
+        .map(|(section, _)| *section)
+        .collect()
+}
+
+// Where running a section can go next: the target of each instruction up to the first ret or jump, and the next
+// section as laid out (with no instruction) when nothing ends it before then. Strings never run, so neither pointers
+// into them nor carrying on into one count.
+fn successors<'a>(
+    ordered_data: &'a OrderedData,
+    string_sections: &HashSet<Pointer>,
+    section: Pointer,
+) -> Vec<(Option<&'a Data>, Pointer)> {
+    let Some((index, _, data)) = ordered_data.get_full(&section) else {
+        return Vec::new();
+    };
+    let end = data.iter().position(Data::is_end);
+    let mut successors = data
+        .iter()
+        .take(end.map_or(data.len(), |last| last + 1))
+        .filter(|datum| !matches!(datum, Data::TxtPtr(_)))
+        .filter_map(|datum| datum.get_pointer().map(|target| (Some(datum), target)))
+        .collect::<Vec<_>>();
+    if end.is_none()
+        && let Some((next, _)) = ordered_data.get_index(index + 1)
+    {
+        successors.push((None, *next));
+    }
+    successors.retain(|(_, target)| !string_sections.contains(target));
+    successors
+}
+
 #[expect(
     clippy::trivially_copy_pass_by_ref,
     reason = "satisfies trait requirement"
@@ -0,0 +1,209 @@
+// Every opcode parse_events knows the layout of. Names are only given where it's clear what the opcode does, which so
+// far is only control flow and text. Everything else goes by op_xx until someone works it out, including the opcodes
+// that carry a target, as where they go is known but not what they do there. Renaming one here renames it everywhere,
+// including in the assembler.
+//
+// An opcode only gets Shape::Ptr once its word has been checked to be an offset into the event, by the evidence of
+// `aeroprism pointers` and a look at the events it comes up in. None has been so far, so they're all still Shape::Word.
+
+pub const OPCODES: &[Opcode] = &[
+    Opcode {
+        code: 0x0a,
+        description: "Ends the current script, or returns from a call",
+        mnemonic: "ret",
+        shape: Shape::Ret,
+    },
+    Opcode {
+        code: 0x0b,
+        description: "Continues the script at the target",
+        mnemonic: "jump",
+        shape: Shape::Branch,
+    },
+    Opcode {
+        code: 0x0c,
+        description: "Runs the target and comes back (JAL-like). The two words are passed along.",
+        mnemonic: "call",
+        shape: Shape::Call,
+    },
+    Opcode {
+        code: 0x0f,
+        description: "Runs the target and comes back, depending on a list of values",
+        mnemonic: "call_multi",
+        shape: Shape::Multi,
+    },
+    Opcode {
+        code: 0x10,
+        description: "Branches to the target when one of a list of values matches (BEQ-like)",
+        mnemonic: "branch_eq",
+        shape: Shape::Multi,
+    },
+    Opcode {
+        code: 0x12,
+        description: "Shows a dialog string",
+        mnemonic: "text",
+        shape: Shape::Text,
+    },
+    Opcode {
+        code: 0x17,
+        description: "Conditionally branches to the target (BNE-like)",
+        mnemonic: "branch_ne",
+        shape: Shape::Branch,
+    },
+    Opcode {
+        code: 0x1e,
+        description: "Conditionally branches to the target (BGTZ-like)",
+        mnemonic: "branch_gtz",
+        shape: Shape::Branch,
+    },
+    Opcode {
+        code: 0x24,
+        description: "Unknown, takes two bytes and a target",
+        mnemonic: "op_24",
+        shape: Shape::Cop,
+    },
+    Opcode {
+        code: 0x25,
+        description: "Unknown, takes two bytes and a target",
+        mnemonic: "op_25",
+        shape: Shape::Cop,
+    },
+    Opcode {
+        code: 0x26,
+        description: "Unknown, takes a byte",
+        mnemonic: "op_26",
+        shape: Shape::Op,
+    },
+    Opcode {
+        code: 0x2a,
+        description: "Unknown, takes a byte and a word",
+        mnemonic: "op_2a",
+        shape: Shape::Word,
+    },
+    Opcode {
+        code: 0x2b,
+        description: "Unknown, takes a byte",
+        mnemonic: "op_2b",
+        shape: Shape::Op,
+    },
+    Opcode {
+        code: 0x2e,
+        description: "Unknown, takes a byte and a word",
+        mnemonic: "op_2e",
+        shape: Shape::Word,
+    },
+    Opcode {
+        code: 0x33,
+        description: "Unknown, takes a byte, a word and a target",
+        mnemonic: "op_33",
+        shape: Shape::Cop2,
+    },
+    Opcode {
+        code: 0x38,
+        description: "Unknown, takes two targets. The second one is listed as a ptr right after it.",
+        mnemonic: "op_38",
+        shape: Shape::Branch,
+    },
+    Opcode {
+        code: 0x40,
+        description: "Unknown, takes a byte and a word",
+        mnemonic: "op_40",
+        shape: Shape::Word,
+    },
+    Opcode {
+        code: 0x41,
+        description: "Unknown, takes two bytes and a target",
+        mnemonic: "op_41",
+        shape: Shape::Cop,
+    },
+    Opcode {
+        code: 0x42,
+        description: "Unknown, takes a byte and a word",
+        mnemonic: "op_42",
+        shape: Shape::Word,
+    },
+    Opcode {
+        code: 0x44,
+        description: "Unknown, takes a byte and a word",
+        mnemonic: "op_44",
+        shape: Shape::Word,
+    },
+    Opcode {
+        code: 0x4a,
+        description: "Unknown, takes a byte, a word and a target",
+        mnemonic: "op_4a",
+        shape: Shape::Cop2,
+    },
+    Opcode {
+        code: 0x4d,
+        description: "Unknown, takes a byte and a word",
+        mnemonic: "op_4d",
+        shape: Shape::Word,
+    },
+    Opcode {
+        code: 0x54,
+        description: "Unknown, takes a byte and a word",
+        mnemonic: "op_54",
+        shape: Shape::Word,
+    },
+];
+
+pub struct Opcode {
+    pub code: u8,
+    pub description: &'static str,
+    pub mnemonic: &'static str,
+    pub shape: Shape,
+}
+
+/// How an opcode's operands are laid out after the opcode word.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+    Branch,
+    Call,
+    Cop,
+    Cop2,
+    Multi,
+    Op,
+    #[expect(
+        dead_code,
+        reason = "no opcode has been checked to take a pointer yet, see the comment at the top"
+    )]
+    Ptr,
+    Ret,
+    Text,
+    Word,
+}
+
+impl Shape {
+    pub const fn layout(self) -> &'static str {
+        match self {
+            Self::Branch => "[op 00 00 00] target",
+            Self::Call => "[op 00 c d] word word target",
+            Self::Cop => "[op 00 c d] target",
+            Self::Cop2 => "[op 00 c 00] word target",
+            Self::Multi => "[op 00 count 00] target word...",
+            Self::Op => "[op 00 c 00]",
+            Self::Ptr => "[op 00 c 00] target",
+            Self::Ret => "[op 00 00 00]",
+            Self::Text => "[op 00 00 00] string",
+            Self::Word => "[op 00 c 00] word",
+        }
+    }
+}
+
+pub fn by_code(code: u8) -> Option<&'static Opcode> {
+    OPCODES.iter().find(|opcode| opcode.code == code)
+}
+
+/// Accepts both the name and the `op_xx` form, so named opcodes can still be written by number.
+pub fn by_mnemonic(mnemonic: &str) -> Option<&'static Opcode> {
+    let lowercase = mnemonic.to_lowercase();
+    OPCODES
+        .iter()
+        .find(|opcode| opcode.mnemonic == lowercase)
+        .or_else(|| {
+            lowercase
+                .strip_prefix("op_")
+                .and_then(|code| u8::from_str_radix(code, 16).ok())
+                .and_then(by_code)
+        })
+}
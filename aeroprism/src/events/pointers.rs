@@ -0,0 +1,180 @@
+use crate::{
+    events::{
+        Data, Offset,
+        codec::{OrderedData, parse_events},
+        disasm::{label, mnemonic},
+    },
+    helpers::read_dat_blobs,
+};
+use alloc::collections::{BTreeMap, BTreeSet};
+use std::{
+    io::{self, Cursor},
+    path::Path,
+};
+
+// Anything smaller is far more likely to be a count or an ID than an offset
+pub const MIN_POINTER: u32 = 0x10;
+// How many different places an opcode's words have to land on before it's taken to be pointing at them
+const MIN_TARGETS: usize = 4;
+
+/// How the words of one opcode with a word operand fall across every event of a DAT.
+#[derive(Default)]
+pub struct OpcodeEvidence {
+    // Words landing right at the start of an instruction or string of their own event
+    pub landed: usize,
+    // Words that don't, be it mid-item, unaligned or past the end
+    pub missed: usize,
+    // Where the landed words point, by event and offset, leaving out any under MIN_POINTER
+    pub targets: BTreeSet<(usize, u32)>,
+}
+
+/// A word that hasn't been accounted for as a pointer, but looks like one.
+pub struct PossiblePointer {
+    // What the target offset holds, if it's the start of something
+    pub landing: Option<String>,
+    pub offset: Offset,
+    pub source: String,
+    pub target: Offset,
+}
+
+impl OpcodeEvidence {
+    /// Every single word lands on something, and in enough different places that it's no coincidence.
+    pub fn is_pointer(&self) -> bool {
+        self.missed == 0 && self.targets.len() >= MIN_TARGETS
+    }
+}
+
+/// Prints how the words of each opcode with one land across a DAT straight from the ISO, then every leftover word that
+/// looks like an offset, for the pointers command.
+#[expect(clippy::print_stdout, reason = "the report is the output")]
+pub fn survey_dat(dat_path: &Path, likely_only: bool) -> Result<(), io::Error> {
+    let blobs = read_dat_blobs(dat_path)?;
+    let (slots, events): (Vec<usize>, Vec<OrderedData>) = blobs
+        .iter()
+        .enumerate()
+        .filter(|(_, blob)| !blob.starts_with(b"SGGG"))
+        .map(|(slot, data)| {
+            parse_events(&mut Cursor::new(data), u32::try_from(data.len()).unwrap())
+                .map(|(ordered_data, _)| (slot, ordered_data))
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+    for (op, evidence) in weigh_opcodes(&events) {
+        println!(
+            "{}: {} of {} words land on the start of something{}",
+            mnemonic(op),
+            evidence.landed,
+            evidence.landed + evidence.missed,
+            if evidence.is_pointer() {
+                ", worth checking as a pointer opcode"
+            } else {
+                ""
+            }
+        );
+    }
+    let (mut total, mut likely) = (0, 0);
+    for (slot, ordered_data) in slots.iter().zip(&events) {
+        for possible in scan(ordered_data) {
+            total += 1;
+            if possible.landing.is_some() {
+                likely += 1;
+            } else if likely_only {
+                continue;
+            }
+            println!(
+                "{slot:04} [{:04x}] {} -> {:04x} ({})",
+                possible.offset,
+                possible.source,
+                possible.target,
+                possible.landing.map_or_else(
+                    || "mid-item".to_owned(),
+                    |landing| format!("lands on {landing}")
+                )
+            );
+        }
+    }
+    println!(
+        "{total} possible pointers, {likely} landing at the start of an instruction or string"
+    );
+    Ok(())
+}
+
+/// Lists every word in raw data and typed operands that could be an offset into the same event. Those landing right at
+/// the start of an instruction or string are the ones most worth a look, as inserting text would silently break them.
+pub fn scan(ordered_data: &OrderedData) -> Vec<PossiblePointer> {
+    let (items, eof) = lay_out(ordered_data);
+
+    let mut possible_pointers = Vec::with_capacity(16);
+    for (&item_offset, datum) in &items {
+        let words = match datum {
+            Data::Unmanaged(bytes) => (item_offset..)
+                .step_by(4)
+                .zip(bytes.chunks_exact(4))
+                .map(|(word_offset, word)| {
+                    (word_offset, u32::from_le_bytes(word.try_into().unwrap()))
+                })
+                .collect::<Vec<_>>(),
+            Data::OpWord(_, _, field) => vec![(item_offset + 4, *field)],
+            _ => continue,
+        };
+        for (word_offset, word) in words {
+            if word < MIN_POINTER || !word.is_multiple_of(4) || word >= eof {
+                continue;
+            }
+            possible_pointers.push(PossiblePointer {
+                landing: items.get(&word).map(|target| describe(target)),
+                offset: word_offset,
+                source: describe(datum),
+                target: word,
+            });
+        }
+    }
+    possible_pointers
+}
+
+/// Weighs up whether the word of each opcode with one is an offset, going by where the words land in every event of a
+/// DAT. Opcodes already given the Ptr shape are left out.
+pub fn weigh_opcodes(events: &[OrderedData]) -> BTreeMap<u8, OpcodeEvidence> {
+    let mut evidence: BTreeMap<u8, OpcodeEvidence> = BTreeMap::new();
+    for (event, ordered_data) in events.iter().enumerate() {
+        let (items, _) = lay_out(ordered_data);
+        for datum in items.values() {
+            let Data::OpWord(op, _, word) = datum else {
+                continue;
+            };
+            let opcode_evidence = evidence.entry(*op).or_default();
+            if items.contains_key(word) {
+                opcode_evidence.landed += 1;
+                if *word >= MIN_POINTER {
+                    opcode_evidence.targets.insert((event, *word));
+                }
+            } else {
+                opcode_evidence.missed += 1;
+            }
+        }
+    }
+    evidence
+}
+
+// Everything is laid out back to back, so offsets come straight from the lengths
+fn lay_out(ordered_data: &OrderedData) -> (BTreeMap<Offset, &Data>, Offset) {
+    let mut items = BTreeMap::new();
+    let mut offset = 0;
+    for datum in ordered_data.values().flatten() {
+        items.insert(offset, datum);
+        offset += Offset::try_from(datum.len()).unwrap();
+    }
+    (items, offset)
+}
+
+fn describe(datum: &Data) -> String {
+    match datum {
+        Data::Ptr(pointer) => format!("ptr {}", label(*pointer)),
+        Data::Ret => "ret".to_owned(),
+        Data::String(_) => "string".to_owned(),
+        Data::TxtPtr(_) => "text".to_owned(),
+        Data::Unmanaged(_) => "raw".to_owned(),
+        _ => datum.opcode().map(mnemonic).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,174 @@
+use crate::events::{
+    ControlCode, Data, DialogItem, DialogString, Pointer,
+    codec::{OrderedData, OrderedDialog},
+    deserialize_u32_hex, serialize_u32_hex,
+};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+/// One option of a `[Select]` prompt, as the script after the prompt branches on it. Only there for reference, as the
+/// branches themselves live in the event data.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Choice {
+    // Section the script goes on to when this option is picked
+    jumps_to: Key,
+    // The first string shown from there, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shows: Option<Key>,
+    // The option's line of the prompt, if the prompt has a line for every option
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    // What the branch compares the picked option against, which is most likely its position in the menu
+    value: u32,
+}
+
+// A symbol, written the same way as dialog keys
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+struct Key(
+    #[serde(
+        serialize_with = "serialize_u32_hex",
+        deserialize_with = "deserialize_u32_hex"
+    )]
+    Pointer,
+);
+
+impl Choice {
+    /// Swaps the symbols this links to for others, like when dialog keys are migrated.
+    pub fn remap<F: FnMut(Pointer) -> Pointer>(&mut self, mut remap: F) {
+        self.jumps_to.0 = remap(self.jumps_to.0);
+        if let Some(shows) = &mut self.shows {
+            shows.0 = remap(shows.0);
+        }
+    }
+}
+
+/// Where the choices noted down in a dialog file no longer add up: a prompt that lost its `[Select]`, or one whose
+/// script now branches on a different number of options.
+pub fn check_choices(ordered_data: &OrderedData, dialog: &OrderedDialog) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (symbol, dialog_string) in dialog {
+        let noted = dialog_string.choices.len();
+        if !has_select(dialog_string) {
+            if noted > 0 {
+                problems.push(format!(
+                    "[{symbol:04x}] lists {noted} choice(s), but has no [Select] any more"
+                ));
+            }
+            continue;
+        }
+        let branched = find_choices(ordered_data, *symbol).len();
+        if noted != branched {
+            problems.push(format!(
+                "[{symbol:04x}] lists {noted} choice(s), but the script branches on {branched}"
+            ));
+        }
+    }
+    problems
+}
+
+// The choices branched on after the first place a string is shown from
+fn find_choices(ordered_data: &OrderedData, symbol: Pointer) -> Vec<Choice> {
+    let mut shown_from = ordered_data.keys().filter_map(|section| {
+        let mut run = script_from(ordered_data, *section);
+        run.position(|datum| matches!(datum, Data::TxtPtr(text) if *text == symbol))?;
+        Some(run)
+    });
+    let Some(after_text) = shown_from.next() else {
+        debug!("[{symbol:04x}] has a [Select], but nothing shows it");
+        return Vec::new();
+    };
+    let mut choices = Vec::new();
+    for datum in after_text {
+        match datum {
+            Data::Multi(0x0f | 0x10, target, values) => {
+                let shows = shown_first(ordered_data, *target).map(Key);
+                choices.extend(values.iter().map(|value| Choice {
+                    jumps_to: Key(*target),
+                    shows,
+                    text: None,
+                    value: *value,
+                }));
+            }
+            // Once the branches are over, anything else is the script getting on with things
+            _ if !choices.is_empty() => break,
+            Data::TxtPtr(_) => break,
+            _ => {}
+        }
+    }
+    choices
+}
+
+fn has_select(dialog_string: &DialogString) -> bool {
+    dialog_string
+        .text
+        .iter()
+        .any(|item| matches!(item, DialogItem::ControlCode(ControlCode::Select)))
+}
+
+/// Fills in the choices of every string with a `[Select]` in it, from the `branch_eq` and `call_multi` opcodes the
+/// script runs right after showing it. Prompts the script doesn't branch on straight away are left without any.
+pub fn link_choices(ordered_data: &OrderedData, ordered_dialog: &mut OrderedDialog) {
+    for (symbol, dialog_string) in ordered_dialog.iter_mut() {
+        if has_select(dialog_string) {
+            let mut choices = find_choices(ordered_data, *symbol);
+            name_choices(dialog_string, &mut choices);
+            dialog_string.choices = choices;
+        }
+    }
+}
+
+// Gives each choice its line of the prompt. The options are taken to be the last lines before the [Select], one per
+// option in menu order, which is the order of the values branched on. Prompts with fewer lines than options keep
+// their question and options apart, so their choices get no text.
+fn name_choices(dialog_string: &DialogString, choices: &mut [Choice]) {
+    let prompt = dialog_string
+        .text
+        .iter()
+        .take_while(|item| !matches!(item, DialogItem::ControlCode(ControlCode::Select)))
+        .map(ToString::to_string)
+        .collect::<String>();
+    let lines = prompt
+        .trim_end_matches('\n')
+        .split('\n')
+        .collect::<Vec<_>>();
+    let mut values = choices
+        .iter()
+        .map(|choice| choice.value)
+        .collect::<Vec<_>>();
+    values.sort_unstable();
+    values.dedup();
+    let Some(first_option) = lines.len().checked_sub(values.len()) else {
+        return;
+    };
+    for choice in choices {
+        let line = values
+            .binary_search(&choice.value)
+            .ok()
+            .and_then(|position| lines.get(first_option + position));
+        choice.text = line.map(|text| text.trim().to_owned());
+    }
+}
+
+// The script starting at a section, carrying on into the next sections until it returns or jumps away
+fn script_from(ordered_data: &OrderedData, section: Pointer) -> impl Iterator<Item = &Data> {
+    let start = ordered_data
+        .get_index_of(&section)
+        .unwrap_or(ordered_data.len());
+    let mut ended = false;
+    ordered_data
+        .values()
+        .skip(start)
+        .flatten()
+        .take_while(move |datum| {
+            let carries_on = !ended && !matches!(datum, Data::String(_));
+            ended = datum.is_end();
+            carries_on
+        })
+}
+
+fn shown_first(ordered_data: &OrderedData, section: Pointer) -> Option<Pointer> {
+    script_from(ordered_data, section).find_map(|datum| match datum {
+        Data::TxtPtr(symbol) => Some(*symbol),
+        _ => None,
+    })
+}
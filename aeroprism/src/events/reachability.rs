@@ -0,0 +1,266 @@
+use crate::events::{
+    Data, Pointer,
+    codec::{OrderedData, OrderedDialog},
+    disasm::{label, snippet},
+    load_event, string_sections, successors,
+};
+use alloc::collections::BTreeSet;
+use core::fmt::Write;
+use log::warn;
+use std::{collections::HashMap, fs, io, path::Path};
+
+// Scripts start at the top of the event, and at any other entry points given. Anything that can't be reached from those
+// by following pointers, or by carrying on into the next label, never runs. The same goes for anything after a ret or a
+// jump that nothing points at. Pointers from other events or the executable can't be seen from here, so unless they're
+// passed in as entry points, treat unreachable as "worth a look" rather than "safe to skip".
+
+/// What in an event never runs or never shows up.
+pub struct Report {
+    // Strings only shown by sections that never run
+    pub dead_strings: Vec<Pointer>,
+    // Pointers to something that isn't the start of an item, as (section, target)
+    pub mid_instruction: Vec<(Pointer, Pointer)>,
+    // Sections holding script that never runs, either all of it or the part after a ret or jump
+    pub unreachable: Vec<Pointer>,
+    // Strings no text instruction shows at all
+    pub unreferenced_strings: Vec<Pointer>,
+}
+
+impl Report {
+    pub const fn is_clean(&self) -> bool {
+        self.dead_strings.is_empty()
+            && self.mid_instruction.is_empty()
+            && self.unreachable.is_empty()
+            && self.unreferenced_strings.is_empty()
+    }
+
+    /// The report as indented lines, with dialog snippets for the strings.
+    pub fn to_text(&self, dialog: Option<&OrderedDialog>) -> String {
+        let describe_string = |symbol: &Pointer| {
+            dialog.and_then(|strings| strings.get(symbol)).map_or_else(
+                || label(*symbol),
+                |dialog_string| format!("{} {}", label(*symbol), snippet(dialog_string)),
+            )
+        };
+        let mut text = String::with_capacity(256);
+        if !self.unreachable.is_empty() {
+            let labels = self.unreachable.iter().map(|section| label(*section));
+            _ = writeln!(
+                text,
+                "  unreachable: {}",
+                labels.collect::<Vec<_>>().join(", ")
+            );
+        }
+        for symbol in &self.dead_strings {
+            _ = writeln!(text, "  dead text: {}", describe_string(symbol));
+        }
+        for symbol in &self.unreferenced_strings {
+            _ = writeln!(text, "  unused text: {}", describe_string(symbol));
+        }
+        for (section, target) in &self.mid_instruction {
+            _ = writeln!(
+                text,
+                "  mid-instruction: {} -> {}",
+                label(*section),
+                label(*target)
+            );
+        }
+        text
+    }
+}
+
+/// Prints what never runs in an event slot, or in every event of an unpacked EVENT.DAT folder, for the reachability
+/// command.
+#[expect(clippy::print_stdout, reason = "the report is the output")]
+pub fn check_events(path: &Path, entries: &[String]) -> Result<(), io::Error> {
+    let entry_points = entries
+        .iter()
+        .map(|entry| {
+            let (slot, entry_label) = entry
+                .split_once(':')
+                .map_or((None, entry.as_str()), |(slot, rest)| (Some(slot), rest));
+            let symbol = entry_label
+                .strip_prefix("L_")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .filter(|_| slot.is_some() || !path.is_dir())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Expected an entry point like L_0040, or 0012:L_0040 for a folder, but got {entry}"
+                        ),
+                    )
+                })?;
+            Ok((slot, symbol))
+        })
+        .collect::<Result<Vec<_>, io::Error>>()?;
+    let slots = if path.is_dir() {
+        let mut slots = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        slots.retain(|slot| slot.to_string_lossy().ends_with("eventdata.json"));
+        slots.sort();
+        slots
+    } else {
+        vec![path.to_path_buf()]
+    };
+    let mut flagged = 0;
+    for slot in &slots {
+        let (title, ordered_data, dialog) = load_event(slot)?;
+        let slot_name = slot.file_name().unwrap_or_default().to_string_lossy();
+        let slot_number = slot_name.split('.').next().unwrap_or_default();
+        let slot_entries = entry_points
+            .iter()
+            .filter(|(entry_slot, _)| entry_slot.is_none_or(|only_slot| only_slot == slot_number))
+            .map(|(_, symbol)| *symbol)
+            .collect::<Vec<_>>();
+        for symbol in &slot_entries {
+            if !ordered_data.contains_key(symbol) {
+                warn!("{title} doesn't have a label {}", label(*symbol));
+            }
+        }
+        let report = analyze(&ordered_data, &slot_entries);
+        if !report.is_clean() {
+            flagged += 1;
+            print!("{title}\n{}", report.to_text(dialog.as_ref()));
+        }
+    }
+    println!(
+        "{flagged} of {} event(s) have unreachable sections, unused text or pointers into the middle of something",
+        slots.len()
+    );
+    Ok(())
+}
+
+/// Follows the script from the top of the event and from `entries`, the labels something outside the event is known to
+/// jump to.
+pub fn analyze(ordered_data: &OrderedData, entries: &[Pointer]) -> Report {
+    let string_sections = string_sections(ordered_data);
+
+    // How many items at the start of each section actually run, up to the first ret or jump
+    let mut runs = HashMap::with_capacity(ordered_data.len());
+    let mut shown_when_reachable = BTreeSet::new();
+    let mut to_visit = ordered_data
+        .keys()
+        .take(1)
+        .chain(entries)
+        .copied()
+        .collect::<Vec<_>>();
+    while let Some(section) = to_visit.pop() {
+        let Some(data) = ordered_data.get(&section) else {
+            continue;
+        };
+        if runs.contains_key(&section) || string_sections.contains(&section) {
+            continue;
+        }
+        let run = data
+            .iter()
+            .position(Data::is_end)
+            .map_or(data.len(), |last| last + 1);
+        runs.insert(section, run);
+        for datum in data.iter().take(run) {
+            if let Data::TxtPtr(symbol) = datum {
+                shown_when_reachable.insert(*symbol);
+            }
+        }
+        to_visit.extend(
+            successors(ordered_data, &string_sections, section)
+                .into_iter()
+                .map(|(_, target)| target),
+        );
+    }
+
+    let mut shown = BTreeSet::new();
+    let mut mid_instruction = Vec::new();
+    let mut unreachable = Vec::new();
+    for (section, data) in ordered_data {
+        // Script nothing points to gets lumped in with whatever comes before it, so it can also hide after a ret, a
+        // jump or a string. Leftover raw bytes there are usually just padding.
+        let run = if string_sections.contains(section) {
+            Some(1)
+        } else {
+            runs.get(section).copied()
+        };
+        let has_dead_script = run.is_none_or(|ran| {
+            data.iter()
+                .skip(ran)
+                .any(|datum| !matches!(datum, Data::Unmanaged(_)))
+        });
+        if has_dead_script {
+            unreachable.push(*section);
+        }
+
+        for datum in data {
+            if let Data::TxtPtr(symbol) = datum {
+                shown.insert(*symbol);
+            }
+            let Some(target) = datum.get_pointer() else {
+                continue;
+            };
+            // Pointers into the middle of an instruction never get a section of their own. Ones into the middle of
+            // raw bytes split them, so the target starts with raw bytes carrying on from the previous section.
+            let lands_mid_item =
+                ordered_data
+                    .get_full(&target)
+                    .is_none_or(|(index, _, target_data)| {
+                        index > 0
+                            && matches!(target_data.first(), Some(Data::Unmanaged(_)))
+                            && matches!(
+                                ordered_data
+                                    .get_index(index - 1)
+                                    .and_then(|(_, previous)| previous.last()),
+                                Some(Data::Unmanaged(_))
+                            )
+                    });
+            if lands_mid_item {
+                mid_instruction.push((*section, target));
+            }
+        }
+    }
+
+    let strings = ordered_data
+        .keys()
+        .filter(|section| string_sections.contains(section));
+    Report {
+        dead_strings: strings
+            .clone()
+            .filter(|symbol| shown.contains(symbol) && !shown_when_reachable.contains(symbol))
+            .copied()
+            .collect(),
+        mid_instruction,
+        unreachable,
+        unreferenced_strings: strings
+            .filter(|symbol| !shown.contains(symbol))
+            .copied()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::analyze;
+    use crate::events::codec::parse_events;
+    use std::io::Cursor;
+
+    const EVENT: [u8; 0x2c] = [
+        0x0b, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, // jump L_0014
+        0x12, 0x00, 0x00, 0x00, 0x24, 0x00, 0x00, 0x00, // text L_0024, never shown
+        0x0a, 0x00, 0x00, 0x00, // ret
+        0x26, 0x00, 0x01, 0x00, // L_0014: op_26, carrying on into L_0018
+        0x0a, 0x00, 0x00, 0x00, // ret
+        0x17, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // branch_ne L_0000
+        0x82, 0xa0, 0x82, 0xa2, b'\\', 0x00, 0x00, 0x00, // L_0024: the string
+    ];
+
+    #[test]
+    fn script_after_a_jump_is_dead() {
+        crate::ENGRISH.get_or_init(|| false);
+        let (ordered_data, _) =
+            parse_events(&mut Cursor::new(EVENT), u32::try_from(EVENT.len()).unwrap()).unwrap();
+        let report = analyze(&ordered_data, &[]);
+        assert_eq!(report.unreachable, vec![0x00, 0x14]);
+        assert_eq!(report.dead_strings, vec![0x24]);
+        assert!(report.unreferenced_strings.is_empty());
+        assert!(report.mid_instruction.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::events::ControlCode;
+use alloc::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// The release profiles built into the binary, used unless others are given with `--releases`.
+pub const DEFAULT_RELEASES: &str = include_str!("../../releases.toml");
+
+/// Records which release a workspace was unpacked with, so repacking it picks the same one.
+pub const RELEASE_FILE: &str = "release.toml";
+
+// See releases.toml for what each of these means
+#[derive(Deserialize)]
+#[serde(try_from = "ProfileEntry")]
+pub struct Profile {
+    english: bool,
+    executables: Vec<String>,
+    symbols: HashMap<u8, ControlCode>,
+}
+
+#[derive(Deserialize)]
+struct ProfileEntry {
+    #[serde(default)]
+    english: bool,
+    #[serde(default)]
+    executables: Vec<String>,
+    #[serde(default)]
+    symbols: BTreeMap<String, u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReleaseFile {
+    release: String,
+}
+
+impl Profile {
+    /// Whether the release's text is stored with the English translation's character table, same as `-e`.
+    pub const fn is_english(&self) -> bool {
+        self.english
+    }
+
+    /// Whether an executable with this fingerprint belongs to the release.
+    pub fn matches(&self, fingerprint: &str) -> bool {
+        self.executables
+            .iter()
+            .any(|executable| executable.eq_ignore_ascii_case(fingerprint))
+    }
+
+    pub fn parse_table(text: &str) -> Result<HashMap<String, Self>, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// The symbol a byte stands for, if the release has one there.
+    pub fn symbol(&self, byte: u8) -> Option<ControlCode> {
+        self.symbols.get(&byte).copied()
+    }
+
+    /// The byte a symbol is stored as, if the release has it.
+    pub fn symbol_byte(&self, symbol: ControlCode) -> Option<u8> {
+        self.symbols
+            .iter()
+            .find(|(_, code)| **code == symbol)
+            .map(|(byte, _)| *byte)
+    }
+}
+
+impl TryFrom<ProfileEntry> for Profile {
+    type Error = String;
+
+    fn try_from(entry: ProfileEntry) -> Result<Self, Self::Error> {
+        let mut symbols = HashMap::with_capacity(entry.symbols.len());
+        for (name, byte) in entry.symbols {
+            let symbol = ControlCode::symbol_from_str(&name)
+                .ok_or_else(|| format!("{name} isn't a symbol tag"))?;
+            if ControlCode::from_shared(byte).is_some() {
+                return Err(format!(
+                    "{name} can't be {byte:#04x}, as every release uses that byte for {}",
+                    ControlCode::from_shared(byte)
+                        .map(|code| code.to_string())
+                        .unwrap_or_default()
+                ));
+            }
+            if let Some(existing) = symbols.insert(byte, symbol) {
+                return Err(format!("{existing} and {name} are both {byte:#04x}"));
+            }
+        }
+        Ok(Self {
+            english: entry.english,
+            executables: entry.executables,
+            symbols,
+        })
+    }
+}
+
+/// The workspace a path is in, going by the release.toml unpacking leaves at its top.
+pub fn find_workspace(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join(RELEASE_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The release a workspace was unpacked with, if it was unpacked by a version that records it.
+pub fn read_recorded(workspace: &Path) -> Result<Option<String>, io::Error> {
+    match fs::read_to_string(workspace.join(RELEASE_FILE)) {
+        Ok(text) => toml::from_str::<ReleaseFile>(&text)
+            .map(|release_file| Some(release_file.release))
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{RELEASE_FILE}: {e}"))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn record(workspace: &Path, name: &str) -> Result<(), io::Error> {
+    let text = toml::to_string(&ReleaseFile {
+        release: name.to_owned(),
+    })
+    .map_err(io::Error::other)?;
+    fs::write(
+        workspace.join(RELEASE_FILE),
+        format!(
+            "# Written by aeroprism, and used to pick the same release profile when repacking or working on the \
+             workspace\n{text}"
+        ),
+    )
+}
@@ -0,0 +1,210 @@
+use crate::events::{
+    Data, Offset, Pointer,
+    codec::OrderedData,
+    disasm::label,
+    keys::{KeyScheme, read_scheme},
+    pointers::MIN_POINTER,
+    rebuild_slot,
+};
+use alloc::collections::BTreeMap;
+use core::fmt::{self, Display};
+use log::warn;
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs, io,
+    path::Path,
+};
+
+/// Where the sections of a rebuilt event ended up, and what still points at where they used to be.
+#[derive(Default)]
+pub struct RelocationReport {
+    // Words holding the old offset of a section that moved, when that's also where another section is now. Either
+    // could be meant, so they're left alone, unless repacking strictly.
+    pub ambiguous_references: Vec<StaleReference>,
+    // Pointers to sections the event doesn't have, which get left out of the rebuilt event entirely
+    pub invalid_pointers: Vec<String>,
+    // Symbol (the section's original offset) and new offset, for every section that moved
+    pub moves: Vec<(Pointer, Offset)>,
+    pub stale_references: Vec<StaleReference>,
+}
+
+/// A raw word that holds the original offset of a section that has since moved, so it most likely needs turning into
+/// a pointer.
+pub struct StaleReference {
+    pub offset: Offset,
+    pub section: Pointer,
+    pub target: Pointer,
+    pub target_offset: Offset,
+}
+
+impl RelocationReport {
+    pub const fn is_clean(&self) -> bool {
+        self.invalid_pointers.is_empty() && self.stale_references.is_empty()
+    }
+}
+
+impl Display for StaleReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:04x}] in {} still holds {:04x}, but {} is at {:04x} now",
+            self.offset,
+            label(self.section),
+            self.target,
+            label(self.target),
+            self.target_offset
+        )
+    }
+}
+
+/// Compares where each section of a laid out event landed against its symbol, which is where it started out with the
+/// offset key scheme. Raw words and typed operands still holding an old offset are what the game would follow to the
+/// wrong place. Pointers held outside the event, in other slots or the executable, can't be seen from here, which is
+/// what the list of moves is for.
+pub fn find_relocations(
+    ordered_data: &OrderedData,
+    offset_tracker: &HashMap<Pointer, Offset>,
+) -> RelocationReport {
+    let mut moves = offset_tracker
+        .iter()
+        .filter(|(symbol, offset)| symbol != offset)
+        .map(|(symbol, offset)| (*symbol, *offset))
+        .collect::<Vec<_>>();
+    moves.sort_unstable();
+    let moved_from = moves.iter().copied().collect::<BTreeMap<_, _>>();
+    // A word landing on wherever a section is now could just as well be meant for that one
+    let current = offset_tracker.values().copied().collect::<HashSet<_>>();
+
+    let mut ambiguous_references = Vec::new();
+    let mut stale_references = Vec::new();
+    let mut item_offset: Offset = 0;
+    for (section, data) in ordered_data {
+        for datum in data {
+            let words = match datum {
+                Data::Unmanaged(bytes) => (item_offset..)
+                    .step_by(4)
+                    .zip(bytes.chunks_exact(4))
+                    .map(|(word_offset, word)| {
+                        (word_offset, u32::from_le_bytes(word.try_into().unwrap()))
+                    })
+                    .collect::<Vec<_>>(),
+                Data::OpWord(_, _, field) => vec![(item_offset + 4, *field)],
+                _ => Vec::new(),
+            };
+            for (word_offset, word) in words {
+                if word < MIN_POINTER {
+                    continue;
+                }
+                if let Some(target_offset) = moved_from.get(&word) {
+                    let stale_reference = StaleReference {
+                        offset: word_offset,
+                        section: *section,
+                        target: word,
+                        target_offset: *target_offset,
+                    };
+                    if current.contains(&word) {
+                        ambiguous_references.push(stale_reference);
+                    } else {
+                        stale_references.push(stale_reference);
+                    }
+                }
+            }
+            item_offset += Offset::try_from(datum.len()).unwrap();
+        }
+    }
+
+    RelocationReport {
+        ambiguous_references,
+        invalid_pointers: Vec::new(),
+        moves,
+        stale_references,
+    }
+}
+
+/// Prints the sections that moved in each event of an unpacked DAT, and the references left pointing at where they were.
+#[expect(clippy::print_stdout, reason = "the report is the output")]
+pub fn report_relocations(dat_dir: &Path) -> Result<(), io::Error> {
+    if read_scheme(dat_dir)? != KeyScheme::Offset {
+        warn!(
+            "{} uses sequential keys, which say nothing about where sections started out, so only invalid pointers can \
+             be found. Run `aeroprism migrate-keys` first for the full report.",
+            dat_dir.display()
+        );
+    }
+    let mut slots = fs::read_dir(dat_dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    slots.retain(|slot| {
+        slot.to_string_lossy().ends_with("eventdata.json")
+            || slot.extension() == Some(OsStr::new("evs"))
+    });
+    slots.sort();
+    let (mut moved, mut unresolved) = (0, 0);
+    for slot in &slots {
+        let data = fs::read(slot)?;
+        let component_file = if slot.extension() == Some(OsStr::new("json")) {
+            slot.with_extension("")
+        } else {
+            slot.clone()
+        };
+        let (_, report) = rebuild_slot(&component_file, &data)?;
+        if report.moves.is_empty() && report.is_clean() && report.ambiguous_references.is_empty() {
+            continue;
+        }
+        println!("{}", slot.file_name().unwrap_or_default().to_string_lossy());
+        for (symbol, offset) in &report.moves {
+            println!(
+                "  {} moved from {symbol:04x} to {offset:04x}",
+                label(*symbol)
+            );
+        }
+        for invalid_pointer in &report.invalid_pointers {
+            println!("  {invalid_pointer}");
+        }
+        for stale_reference in &report.stale_references {
+            println!("  {stale_reference}");
+        }
+        for ambiguous_reference in &report.ambiguous_references {
+            println!("  {ambiguous_reference}, unless it's meant for what's there now");
+        }
+        if !report.moves.is_empty() {
+            moved += 1;
+        }
+        if !report.is_clean() {
+            unresolved += 1;
+        }
+    }
+    println!(
+        "{moved} of {} event(s) have sections that moved, {unresolved} have references that weren't updated. Moved \
+         sections pointed at from other events or the executable need those pointers updated by hand.",
+        slots.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StaleReference, find_relocations};
+    use crate::events::{Data, codec::OrderedData};
+    use std::collections::HashMap;
+
+    #[test]
+    fn old_offsets_taken_over_by_another_section_are_ambiguous() {
+        // L_0010 moved to 0x20, where L_0020 used to be, and L_0020 moved on to 0x24
+        let offset_tracker = HashMap::from([(0x00, 0x00), (0x10, 0x20), (0x20, 0x24)]);
+        let words = [0x10u32, 0x20].iter().flat_map(|word| word.to_le_bytes());
+        let ordered_data = OrderedData::from([(0x00, vec![Data::Unmanaged(words.collect())])]);
+
+        let report = find_relocations(&ordered_data, &offset_tracker);
+        let targets = |references: &[StaleReference]| {
+            references
+                .iter()
+                .map(|reference| reference.target)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(targets(&report.stale_references), vec![0x10]);
+        assert_eq!(targets(&report.ambiguous_references), vec![0x20]);
+        assert!(!report.is_clean());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{
+    events::{
+        Color, DialogString,
+        lint::{Font, load_font},
+        load_event,
+        sjis_map::utf8_to_ps2,
+    },
+    font::{GlyphSheet, load_glyph_sheet},
+    gallery::load_image,
+    maprender::write_rgba_png,
+    sggg_codec::rgba_palette,
+};
+use alloc::collections::BTreeSet;
+use log::{info, warn};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const BACKGROUND: [u8; 4] = [0x18, 0x20, 0x68, 0xFF];
+const FRAME: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+// Between boxes, and around the whole preview
+const GAP: u32 = 8;
+// Between the frame and the text
+const MARGIN: u32 = 8;
+const PLACEHOLDER: [u8; 4] = [0x90, 0x90, 0x90, 0xFF];
+
+// Everything the preview can't work out by itself. Paths are relative to the spec file, and every entry is optional.
+// Example:
+//
+// glyph_spec = "font.toml"               # The font spec the glyph sheet was exported with
+// glyph_sheet = "psg2_font/font.png"     # Written by font-export. Without one, glyphs are drawn as blocks.
+// line_height = 18                       # Defaults to the height of a glyph, or 16 without a sheet
+//
+// [portraits]                            # Which extracted image each portrait number shows
+// 86 = "psg2_data/EVENT.DAT/0123.lz77.png"
+#[derive(Deserialize, Default)]
+pub struct PreviewSpec {
+    glyph_sheet: Option<PathBuf>,
+    glyph_spec: Option<PathBuf>,
+    line_height: Option<u32>,
+    #[serde(default)]
+    portraits: HashMap<String, PathBuf>,
+}
+
+// The pixels of a preview, with the drawing it needs
+struct Canvas {
+    pixels: Vec<[u8; 4]>,
+    width: u32,
+}
+
+impl Canvas {
+    // Mixes a color in by how much ink there is
+    fn blend(&mut self, x: u32, y: u32, color: [u8; 4], level: u8) {
+        if x >= self.width || level == 0 {
+            return;
+        }
+        if let Some(pixel) = self.pixels.get_mut((y * self.width + x) as usize) {
+            let opacity = u16::from(level);
+            let under = *pixel;
+            for ((channel, under_channel), over_channel) in pixel.iter_mut().zip(under).zip(color) {
+                *channel = u8::try_from(
+                    (u16::from(under_channel) * (255 - opacity)
+                        + u16::from(over_channel) * opacity)
+                        / 255,
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 4]) {
+        for row in y..y + height {
+            for column in x..x + width {
+                self.blend(column, row, color, u8::MAX);
+            }
+        }
+    }
+
+    fn frame(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 4]) {
+        self.fill(x, y, width, 2, color);
+        self.fill(x, y + height - 2, width, 2, color);
+        self.fill(x, y, 2, height, color);
+        self.fill(x + width - 2, y, 2, height, color);
+    }
+}
+
+/// Draws a dialog string to a PNG the way the text box shows it, a box per [More], with its portraits and colors.
+/// Lines and boxes are laid out with the same font as `lint`, so whatever runs past the frame there runs past it here.
+pub fn render_preview(
+    dialog_string: &DialogString,
+    font: &Font,
+    spec_path: Option<&Path>,
+    out_path: &Path,
+) -> Result<(), io::Error> {
+    let spec_dir = spec_path
+        .and_then(Path::parent)
+        .unwrap_or_else(|| Path::new("."));
+    let spec = match spec_path {
+        Some(path) => toml::from_str::<PreviewSpec>(&fs::read_to_string(path)?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", path.display()),
+            )
+        })?,
+        None => PreviewSpec::default(),
+    };
+    let glyphs = match (&spec.glyph_spec, &spec.glyph_sheet) {
+        (Some(glyph_spec), Some(glyph_sheet)) => Some(load_glyph_sheet(
+            &spec_dir.join(glyph_spec),
+            &spec_dir.join(glyph_sheet),
+        )?),
+        (None, None) => None,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "glyph_spec and glyph_sheet go together",
+            ));
+        }
+    };
+    let line_height = spec
+        .line_height
+        .or_else(|| glyphs.as_ref().map(GlyphSheet::glyph_height))
+        .unwrap_or(16)
+        .max(1);
+
+    let pages = font.lay_out(dialog_string);
+    let box_lines = u32::try_from(font.box_lines).unwrap();
+    // A square left of the box, where the portrait shows
+    let portrait_side = box_lines * line_height + 2 * MARGIN;
+    let portrait_column = if pages.iter().any(|page| page.portrait.is_some()) {
+        portrait_side + GAP
+    } else {
+        0
+    };
+    // Anything running past the frame gets drawn anyway, so the canvas grows to fit it
+    let width = GAP
+        + portrait_column
+        + pages
+            .iter()
+            .flat_map(|page| page.line_widths.iter().map(|line| page.indent + line))
+            .fold(font.box_width, u32::max)
+        + 2 * MARGIN
+        + GAP;
+    let page_heights = pages
+        .iter()
+        .map(|page| {
+            u32::try_from(page.line_widths.len())
+                .unwrap()
+                .max(box_lines)
+                * line_height
+                + 2 * MARGIN
+        })
+        .collect::<Vec<_>>();
+    let height = GAP
+        + page_heights
+            .iter()
+            .map(|page_height| page_height + GAP)
+            .sum::<u32>();
+    let mut canvas = Canvas {
+        pixels: vec![[0; 4]; (width * height) as usize],
+        width,
+    };
+
+    let missing = pages
+        .iter()
+        .filter_map(|page| page.portrait)
+        .filter(|portrait| !spec.portraits.contains_key(*portrait))
+        .collect::<BTreeSet<_>>();
+    for portrait in missing {
+        warn!("No image for [Portrait{portrait}]. Add one under [portraits] in the preview spec.");
+    }
+
+    let mut top = GAP;
+    for (page, page_height) in pages.iter().zip(page_heights) {
+        let box_left = GAP + portrait_column;
+        let box_height = box_lines * line_height + 2 * MARGIN;
+        canvas.fill(
+            box_left,
+            top,
+            font.box_width + 2 * MARGIN,
+            box_height,
+            BACKGROUND,
+        );
+        canvas.frame(
+            box_left,
+            top,
+            font.box_width + 2 * MARGIN,
+            box_height,
+            FRAME,
+        );
+        if let Some(portrait) = page.portrait {
+            draw_portrait(
+                &mut canvas,
+                &spec,
+                spec_dir,
+                portrait,
+                (GAP, top),
+                portrait_side,
+            )?;
+        }
+        for placed in &page.placed {
+            let x = box_left + MARGIN + page.indent + placed.x;
+            let y = top + MARGIN + u32::try_from(placed.line).unwrap() * line_height;
+            let color = color_rgba(placed.color);
+            match (placed.text.and_then(glyph_code), &glyphs) {
+                (Some(code), Some(sheet)) if sheet.level(code, 0, 0).is_some() => {
+                    for glyph_y in 0..sheet.glyph_height().min(line_height) {
+                        for glyph_x in 0..sheet.glyph_width() {
+                            let level = sheet.level(code, glyph_x, glyph_y).unwrap_or_default();
+                            canvas.blend(x + glyph_x, y + glyph_y, color, level);
+                        }
+                    }
+                }
+                _ => canvas.fill(
+                    x + 1,
+                    y + 2,
+                    placed.width.saturating_sub(2),
+                    line_height.saturating_sub(4),
+                    color,
+                ),
+            }
+        }
+        top += page_height + GAP;
+    }
+
+    write_rgba_png(out_path, width, height, &canvas.pixels)?;
+    info!("Rendered {} box(es) to {}", pages.len(), out_path.display());
+    Ok(())
+}
+
+/// Renders the text boxes of one dialog string in an unpacked event, picked by its key.
+pub fn preview_string(
+    event: &Path,
+    key: &str,
+    chosen_font: Option<String>,
+    fonts_path: Option<&Path>,
+    spec_path: Option<&Path>,
+    out_path: &Path,
+) -> Result<(), io::Error> {
+    let (_, font) = load_font(chosen_font, fonts_path)?;
+    let (title, _, dialog) = load_event(event)?;
+    let dialog_string = u32::from_str_radix(key, 16)
+        .ok()
+        .and_then(|symbol| dialog?.swap_remove(&symbol))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{title} has no dialog under [{key}]"),
+            )
+        })?;
+    render_preview(&dialog_string, &font, spec_path, out_path)
+}
+
+// What each [Color] tag looks like in game, more or less
+const fn color_rgba(color: Color) -> [u8; 4] {
+    match color {
+        Color::Blue => [0x50, 0x90, 0xFF, 0xFF],
+        Color::Red => [0xFF, 0x50, 0x50, 0xFF],
+        Color::Purple => [0xC8, 0x70, 0xFF, 0xFF],
+        Color::Green => [0x50, 0xE8, 0x50, 0xFF],
+        Color::Cyan => [0x50, 0xE8, 0xE8, 0xFF],
+        Color::Yellow => [0xFF, 0xE8, 0x40, 0xFF],
+        Color::White => [0xFF, 0xFF, 0xFF, 0xFF],
+    }
+}
+
+fn draw_portrait(
+    canvas: &mut Canvas,
+    spec: &PreviewSpec,
+    spec_dir: &Path,
+    portrait: &str,
+    (left, top): (u32, u32),
+    side: u32,
+) -> Result<(), io::Error> {
+    canvas.fill(left, top, side, side, BACKGROUND);
+    canvas.frame(left, top, side, side, FRAME);
+    let Some(image_path) = spec.portraits.get(portrait).map(|path| spec_dir.join(path)) else {
+        canvas.fill(
+            left + side / 4,
+            top + side / 4,
+            side / 2,
+            side / 2,
+            PLACEHOLDER,
+        );
+        return Ok(());
+    };
+    let dat_name = image_path
+        .parent()
+        .and_then(Path::file_name)
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let image = load_image(&dat_name, &image_path)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} isn't an image", image_path.display()),
+        )
+    })?;
+    let palette = rgba_palette(image.palette(0).unwrap_or_default());
+    let (image_width, image_height) = image.dimensions();
+    let pixels = image.crop(0, 0, image_width, image_height);
+    // Scaled to fit inside the frame, keeping its shape
+    let inner = side - 2 * MARGIN;
+    let longest = image_width.max(image_height).max(1);
+    for y in 0..inner {
+        for x in 0..inner {
+            let (source_x, source_y) = (x * longest / inner, y * longest / inner);
+            if source_x >= image_width || source_y >= image_height {
+                continue;
+            }
+            let index = pixels
+                .get((source_y * image_width + source_x) as usize)
+                .copied()
+                .unwrap_or_default();
+            let color = palette.get(usize::from(index)).copied().unwrap_or_default();
+            canvas.blend(left + MARGIN + x, top + MARGIN + y, color, color[3]);
+        }
+    }
+    Ok(())
+}
+
+// The bytes a grapheme is stored as, read as one number, which is what glyph sheets are indexed by
+fn glyph_code(grapheme: &str) -> Option<u32> {
+    let custom = crate::CHARMAP
+        .get()
+        .and_then(|charmap| charmap.encode(grapheme))
+        .filter(|(length, _)| *length == grapheme.len())
+        .map(|(_, encoded)| encoded);
+    let bytes = custom.or_else(|| utf8_to_ps2(grapheme))?;
+    Some(
+        bytes
+            .iter()
+            .fold(0, |code, byte| (code << 8) | u32::from(*byte)),
+    )
+}
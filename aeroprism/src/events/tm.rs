@@ -0,0 +1,363 @@
+use crate::events::{
+    IndexMapWrapper,
+    bilingual::counterpart_event,
+    dialog_files,
+    disasm::snippet,
+    gettext::{dialog_text, set_dialog_text},
+    load_dialog_strings, load_event, save_dialog_strings,
+};
+use alloc::collections::BTreeMap;
+use core::{cmp::Ordering, mem};
+use log::warn;
+use std::{io, path::Path};
+
+// A translation memory over every string of a workspace, keyed by the Japanese source text, tags and all. Strings
+// repeat a lot across events (shop greetings, consult lines), so a line translated once can be copied to all the
+// others, and a line translated two different ways is most likely a mistake.
+
+/// A string of a workspace, with its translation when it has one.
+pub struct MemoryEntry {
+    // DAT/slot/key, e.g. EVENT.DAT/0012/01a4
+    pub context: String,
+    pub source: String,
+    // None while the string hasn't been translated yet
+    pub translation: Option<String>,
+}
+
+/// Strings sharing the exact same source, as indexes into the entries.
+pub struct Duplicates {
+    pub entries: Vec<usize>,
+    // Every different translation they've been given, with the strings that have it
+    pub translations: BTreeMap<String, Vec<usize>>,
+}
+
+/// Two different sources that are nearly the same, by the first string of each.
+pub struct FuzzyMatch {
+    pub first: usize,
+    pub second: usize,
+    // How alike they are, in percent
+    pub similarity: usize,
+}
+
+impl Duplicates {
+    /// The one translation the group has been given so far, which can go to the rest of it.
+    pub fn translation(&self) -> Option<&str> {
+        let mut translations = self.translations.keys();
+        match (translations.next(), translations.next()) {
+            (Some(translation), None) => Some(translation),
+            _ => None,
+        }
+    }
+
+    /// The strings still waiting for a translation.
+    pub fn untranslated<'entries>(
+        &self,
+        entries: &'entries [MemoryEntry],
+    ) -> impl Iterator<Item = usize> + 'entries {
+        self.entries.clone().into_iter().filter(|index| {
+            entries
+                .get(*index)
+                .is_some_and(|entry| entry.translation.is_none())
+        })
+    }
+}
+
+/// Every source that more than one string has, in the order they first show up.
+pub fn find_duplicates(entries: &[MemoryEntry]) -> Vec<Duplicates> {
+    let mut by_source: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        by_source.entry(&entry.source).or_default().push(index);
+    }
+    let mut groups = by_source
+        .into_values()
+        .filter(|indexes| indexes.len() > 1)
+        .map(|indexes| {
+            let mut translations: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+            for index in &indexes {
+                if let Some(translation) = entries.get(*index).and_then(|e| e.translation.clone()) {
+                    translations.entry(translation).or_default().push(*index);
+                }
+            }
+            Duplicates {
+                entries: indexes,
+                translations,
+            }
+        })
+        .collect::<Vec<_>>();
+    groups.sort_unstable_by_key(|group| group.entries.first().copied());
+    groups
+}
+
+/// Pairs of different sources at least `min_similarity` percent alike, going by how many characters would need
+/// changing to turn one into the other.
+pub fn find_fuzzy(entries: &[MemoryEntry], min_similarity: usize) -> Vec<FuzzyMatch> {
+    let mut firsts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        firsts.entry(&entry.source).or_insert(index);
+    }
+    let mut sources = firsts
+        .into_iter()
+        .map(|(source, index)| {
+            let chars = source.chars().collect::<Vec<_>>();
+            let mut sorted = chars.clone();
+            sorted.sort_unstable();
+            (index, chars, sorted)
+        })
+        .filter(|(_, chars, _)| !chars.is_empty())
+        .collect::<Vec<_>>();
+    sources.sort_unstable_by_key(|(_, chars, _)| chars.len());
+
+    let mut matches = Vec::new();
+    for (position, (first, shorter, sorted_shorter)) in sources.iter().enumerate() {
+        for (second, longer, sorted_longer) in sources.iter().skip(position + 1) {
+            // Sorted by length, so everything from here on is too long to be alike
+            if shorter.len() * 100 < longer.len() * min_similarity {
+                break;
+            }
+            let allowed = longer.len() * (100 - min_similarity.min(100)) / 100;
+            // Characters one has and the other doesn't need an edit each, which rules most pairs out cheaply
+            if unshared(sorted_shorter, sorted_longer) > allowed {
+                continue;
+            }
+            if let Some(distance) = edit_distance(shorter, longer, allowed) {
+                matches.push(FuzzyMatch {
+                    first: (*first).min(*second),
+                    second: (*first).max(*second),
+                    similarity: 100 - distance * 100 / longer.len(),
+                });
+            }
+        }
+    }
+    matches.sort_unstable_by_key(|fuzzy| (fuzzy.first, fuzzy.second));
+    matches
+}
+
+/// Prints the strings of a workspace that share a source or nearly do, and how each is translated in the translation
+/// workspace. With `--propagate`, copies a shared source's one translation to the strings still missing it.
+#[expect(clippy::print_stdout, reason = "the report is the output")]
+pub fn survey_translations(
+    workspace: &Path,
+    translation: Option<&Path>,
+    propagate: bool,
+    min_similarity: usize,
+) -> Result<(), io::Error> {
+    let mut entries = Vec::new();
+    let mut previews = Vec::new();
+    // The dialog file and key each string's translation lives under, when the translation workspace lines up
+    let mut targets = Vec::new();
+    for (dat_name, slot, _) in dialog_files(workspace)? {
+        let (_, ordered_data, dialog) = load_event(&workspace.join(&dat_name).join(&slot))?;
+        let counterpart = match translation {
+            Some(translation_workspace) => counterpart_event(
+                &ordered_data,
+                &translation_workspace.join(&dat_name).join(&slot),
+            )?,
+            None => None,
+        };
+        if translation.is_some() && counterpart.is_none() {
+            warn!("{dat_name}/{slot}: the translated event doesn't line up with the original one");
+        }
+        for (symbol, dialog_string) in dialog.unwrap_or_default() {
+            let source = dialog_text(&dialog_string);
+            let target = counterpart
+                .as_ref()
+                .and_then(|(dialog_path, symbols, other_strings)| {
+                    let other_symbol = *symbols.get(&symbol)?;
+                    let text = dialog_text(other_strings.get(&other_symbol)?);
+                    Some((dialog_path.clone(), other_symbol, text))
+                });
+            entries.push(MemoryEntry {
+                context: format!("{dat_name}/{slot}/{symbol:04x}"),
+                translation: target
+                    .as_ref()
+                    .map(|(_, _, text)| text.clone())
+                    .filter(|text| *text != source),
+                source,
+            });
+            previews.push(snippet(&dialog_string));
+            targets.push(target.map(|(dialog_path, other_symbol, _)| (dialog_path, other_symbol)));
+        }
+    }
+
+    let context = |index: &usize| {
+        entries
+            .get(*index)
+            .map(|entry| entry.context.as_str())
+            .unwrap_or_default()
+    };
+    let duplicates = find_duplicates(&entries);
+    let (mut inconsistent, mut fillable) = (0, 0);
+    let mut dialog_files = BTreeMap::new();
+    for group in &duplicates {
+        let contexts = group.entries.iter().map(context).collect::<Vec<_>>();
+        let preview = group
+            .entries
+            .first()
+            .and_then(|index| previews.get(*index))
+            .map(String::as_str)
+            .unwrap_or_default();
+        println!(
+            "Same source in {} strings: {} \"{preview}\"",
+            contexts.len(),
+            contexts.join(", ")
+        );
+        if group.translations.len() > 1 {
+            inconsistent += 1;
+            println!("  Translated {} ways:", group.translations.len());
+            for (translated, indexes) in &group.translations {
+                let translated_in = indexes.iter().map(context).collect::<Vec<_>>();
+                println!(
+                    "    \"{}\" in {}",
+                    translated.replace('\n', "\\n"),
+                    translated_in.join(", ")
+                );
+            }
+        }
+        let Some(translated) = group.translation() else {
+            continue;
+        };
+        for index in group.untranslated(&entries) {
+            let Some((dialog_path, other_symbol)) = targets.get(index).cloned().flatten() else {
+                continue;
+            };
+            if !propagate {
+                fillable += 1;
+                continue;
+            }
+            if !dialog_files.contains_key(&dialog_path) {
+                let dialog = load_dialog_strings(&dialog_path)?;
+                dialog_files.insert(dialog_path.clone(), dialog);
+            }
+            // Only counted once the translation is in, so the total is what actually got copied
+            let Some(dialog_string) = dialog_files
+                .get_mut(&dialog_path)
+                .and_then(|dialog| dialog.get_mut(&other_symbol))
+            else {
+                warn!(
+                    "{}: not in {} any more, skipping",
+                    context(&index),
+                    dialog_path.display()
+                );
+                continue;
+            };
+            match set_dialog_text(dialog_string, translated) {
+                Ok(()) => fillable += 1,
+                Err(e) => warn!("{}: {e}, skipping", context(&index)),
+            }
+        }
+    }
+    let fuzzy = find_fuzzy(&entries, min_similarity);
+    for fuzzy_match in &fuzzy {
+        // Whichever one is translated is a head start on the other
+        let hint = [fuzzy_match.first, fuzzy_match.second]
+            .iter()
+            .find_map(|index| entries.get(*index)?.translation.as_ref())
+            .map(|translated| {
+                format!(
+                    ", one translated as \"{}\"",
+                    translated.replace('\n', "\\n")
+                )
+            })
+            .unwrap_or_default();
+        println!(
+            "{}% alike: {} and {}{hint}",
+            fuzzy_match.similarity,
+            context(&fuzzy_match.first),
+            context(&fuzzy_match.second)
+        );
+    }
+    for (dialog_path, dialog) in dialog_files {
+        save_dialog_strings(&dialog_path, &IndexMapWrapper(dialog))?;
+    }
+
+    println!(
+        "{} source(s) shared by more than one string, {inconsistent} of them translated more than one way. {} pair(s) of near duplicates.",
+        duplicates.len(),
+        fuzzy.len()
+    );
+    if propagate {
+        println!("Copied translations to {fillable} string(s)");
+    } else if fillable > 0 {
+        println!(
+            "{fillable} untranslated string(s) share a source with a translated one, --propagate fills them in"
+        );
+    }
+    Ok(())
+}
+
+// Levenshtein distance, or None as soon as it's sure to be more than `allowed`
+fn edit_distance(shorter: &[char], longer: &[char], allowed: usize) -> Option<usize> {
+    let mut previous = (0..=shorter.len()).collect::<Vec<_>>();
+    let mut current = vec![0; shorter.len() + 1];
+    for (row, long_char) in longer.iter().enumerate() {
+        if let Some(first) = current.first_mut() {
+            *first = row + 1;
+        }
+        for (column, short_char) in shorter.iter().enumerate() {
+            let substitution = previous.get(column).copied().unwrap_or_default()
+                + usize::from(short_char != long_char);
+            let deletion = previous.get(column + 1).copied().unwrap_or_default() + 1;
+            let insertion = current.get(column).copied().unwrap_or_default() + 1;
+            if let Some(cell) = current.get_mut(column + 1) {
+                *cell = substitution.min(deletion).min(insertion);
+            }
+        }
+        if current.iter().min().is_some_and(|best| *best > allowed) {
+            return None;
+        }
+        mem::swap(&mut previous, &mut current);
+    }
+    previous
+        .last()
+        .copied()
+        .filter(|distance| *distance <= allowed)
+}
+
+// How many characters of the longer of two sorted lists have no counterpart in the other
+fn unshared(sorted_shorter: &[char], sorted_longer: &[char]) -> usize {
+    let (mut shared, mut short_index, mut long_index) = (0, 0, 0);
+    while let (Some(short_char), Some(long_char)) = (
+        sorted_shorter.get(short_index),
+        sorted_longer.get(long_index),
+    ) {
+        match short_char.cmp(long_char) {
+            Ordering::Less => short_index += 1,
+            Ordering::Greater => long_index += 1,
+            Ordering::Equal => {
+                shared += 1;
+                short_index += 1;
+                long_index += 1;
+            }
+        }
+    }
+    sorted_longer.len() - shared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::edit_distance;
+
+    fn distance(shorter: &str, longer: &str, allowed: usize) -> Option<usize> {
+        edit_distance(
+            &shorter.chars().collect::<Vec<_>>(),
+            &longer.chars().collect::<Vec<_>>(),
+            allowed,
+        )
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(distance("kitten", "sitting", 5), Some(3));
+        assert_eq!(distance("", "abc", 5), Some(3));
+        assert_eq!(distance("same", "same", 0), Some(0));
+        assert_eq!(distance("こんにちは", "こんばんは", 5), Some(2));
+        assert_eq!(distance("abc", "abxcy", 5), Some(2));
+    }
+
+    #[test]
+    fn edit_distance_gives_up_past_the_allowance() {
+        assert_eq!(distance("kitten", "sitting", 2), None);
+        assert_eq!(distance("abc", "xyz", 2), None);
+        assert_eq!(distance("kitten", "sitting", 3), Some(3));
+    }
+}
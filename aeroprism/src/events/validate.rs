@@ -0,0 +1,186 @@
+use crate::events::{
+    DialogItem, DialogString, IndexMapWrapper, Pointer,
+    codec::OrderedDialog,
+    dialog_files, find_event_files, load_dialog_strings, load_event_files,
+    prompts::check_choices,
+    save_dialog_strings,
+    sjis_map::{PS2_ENGRISH_VALS, utf8_to_ps2},
+};
+use core::fmt::{self, Display};
+use std::{collections::HashMap, fs, io, path::Path};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The substitutions built into the binary, used unless others are given with `--substitutions`.
+pub const DEFAULT_SUBSTITUTIONS: &str = include_str!("../../substitutions.toml");
+
+/// A character in a dialog string that has no encoding, so the string can't be repacked. Or, when checking English
+/// text, one that would show up as a full width Japanese glyph.
+pub struct Unencodable {
+    // Counted in characters, tags included, as the text reads in the dialog file
+    pub column: usize,
+    pub encodable: bool,
+    pub grapheme: String,
+    // Within the string, starting from 1
+    pub line: usize,
+    pub symbol: Pointer,
+}
+
+impl Display for Unencodable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code_points = self
+            .grapheme
+            .chars()
+            .map(|c| format!("U+{:04X}", u32::from(c)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let problem = if self.encodable {
+            "isn't in the English font"
+        } else {
+            "can't be encoded"
+        };
+        write!(
+            f,
+            "[{:04x}] line {} column {}: \"{}\" ({code_points}) {problem}",
+            self.symbol, self.line, self.column, self.grapheme
+        )
+    }
+}
+
+/// Every character of every string in a dialog file that can't be encoded, or with `english`, that the English font
+/// doesn't have.
+pub fn find_unencodable(dialog: &OrderedDialog, english: bool) -> Vec<Unencodable> {
+    let mut found = Vec::new();
+    for (symbol, dialog_string) in dialog {
+        let (mut line, mut column) = (1, 1);
+        for item in &dialog_string.text {
+            let DialogItem::String(string) = item else {
+                column += item.to_string().chars().count();
+                continue;
+            };
+            for grapheme in string.graphemes(true) {
+                if grapheme == "\n" {
+                    line += 1;
+                    column = 1;
+                    continue;
+                }
+                if !is_supported(grapheme, english) {
+                    found.push(Unencodable {
+                        column,
+                        encodable: utf8_to_ps2(grapheme).is_some(),
+                        grapheme: grapheme.to_owned(),
+                        line,
+                        symbol: *symbol,
+                    });
+                }
+                column += grapheme.chars().count();
+            }
+        }
+    }
+    found
+}
+
+// The English release only has glyphs for ASCII, plus whatever a custom charmap adds. Anything else from the Japanese
+// encoding still works, but looks out of place.
+fn is_supported(grapheme: &str, english: bool) -> bool {
+    let in_charmap = crate::CHARMAP
+        .get()
+        .and_then(|charmap| charmap.encode(grapheme))
+        .is_some_and(|(length, _)| length == grapheme.len());
+    if in_charmap {
+        true
+    } else if english {
+        PS2_ENGRISH_VALS.contains(&grapheme)
+    } else {
+        utf8_to_ps2(grapheme).is_some()
+    }
+}
+
+pub fn parse_substitutions(text: &str) -> Result<HashMap<String, String>, String> {
+    toml::from_str(text).map_err(|e| e.to_string())
+}
+
+/// Swaps out every character `find_unencodable` would report that has a substitution. Returns how many were swapped.
+pub fn substitute(
+    dialog_string: &mut DialogString,
+    substitutions: &HashMap<String, String>,
+    english: bool,
+) -> usize {
+    let mut swapped = 0;
+    for item in &mut dialog_string.text {
+        let DialogItem::String(string) = item else {
+            continue;
+        };
+        let mut replaced = String::with_capacity(string.len());
+        for grapheme in string.graphemes(true) {
+            match substitutions.get(grapheme) {
+                Some(substitution) if !is_supported(grapheme, english) => {
+                    swapped += 1;
+                    replaced.push_str(substitution);
+                }
+                _ => replaced.push_str(grapheme),
+            }
+        }
+        *string = replaced;
+    }
+    swapped
+}
+
+/// Prints every character in the workspace's dialog that can't be repacked and every [Select] prompt that doesn't match
+/// its script, swapping in substitutions first with `--fix`.
+#[expect(clippy::print_stdout, reason = "the report is the output")]
+pub fn check_workspace(
+    workspace: &Path,
+    fix: bool,
+    substitutions_path: Option<&Path>,
+) -> Result<(), io::Error> {
+    let substitutions_text = match substitutions_path {
+        Some(path) => fs::read_to_string(path)?,
+        None => DEFAULT_SUBSTITUTIONS.to_owned(),
+    };
+    let substitutions = parse_substitutions(&substitutions_text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("substitutions.toml: {e}"),
+        )
+    })?;
+    let english = *crate::ENGRISH.get().unwrap();
+    let (mut swapped, mut remaining, mut mismatched) = (0, 0, 0);
+    for (dat_name, slot, file_name) in dialog_files(workspace)? {
+        let dialog_path = workspace.join(&dat_name).join(&file_name);
+        let mut dialog = load_dialog_strings(&dialog_path)?;
+        if fix {
+            let swapped_here = dialog
+                .values_mut()
+                .map(|dialog_string| substitute(dialog_string, &substitutions, english))
+                .sum::<usize>();
+            if swapped_here > 0 {
+                swapped += swapped_here;
+                save_dialog_strings(&dialog_path, &IndexMapWrapper(dialog.clone()))?;
+            }
+        }
+        for unencodable in find_unencodable(&dialog, english) {
+            remaining += 1;
+            let fix_hint = substitutions
+                .get(&unencodable.grapheme)
+                .map(|substitution| format!(", --fix swaps it for \"{substitution}\""))
+                .unwrap_or_default();
+            println!("{dat_name}/{file_name} {unencodable}{fix_hint}");
+        }
+        let (data_path, _) = find_event_files(&workspace.join(&dat_name).join(&slot))?;
+        let (ordered_data, _) = load_event_files(&data_path, &dialog_path)?;
+        for problem in check_choices(&ordered_data, &dialog) {
+            mismatched += 1;
+            println!("{dat_name}/{file_name} {problem}");
+        }
+    }
+    if fix {
+        println!("Swapped {swapped} character(s)");
+    }
+    if english {
+        println!("{remaining} character(s) can't be encoded or aren't in the English font");
+    } else {
+        println!("{remaining} character(s) can't be encoded");
+    }
+    println!("{mismatched} [Select] prompt(s) don't match the choices their script branches on");
+    Ok(())
+}
@@ -0,0 +1,416 @@
+#![expect(clippy::single_call_fn, reason = "readability")]
+use crate::events::sjis_map::{byte_to_engrish, byte_to_sjis, word_to_sjis};
+use alloc::collections::BTreeMap;
+use log::info;
+use png::{BitDepth, ColorType, Compression, Transformations};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+const FONTS_FILE: &str = "fonts.toml";
+const SHEET_FILE: &str = "font.png";
+const WIDTHS_FILE: &str = "widths.toml";
+
+// Where the dialog font lives hasn't been pinned down yet, so for now its offset and layout have to be found by hand
+// and written into a spec file, the same way maps are. Paths are relative to the spec file. If it turns out to be an
+// SGGG image, it's already unpacked as a PNG and none of this is needed. Example:
+//
+// file = "SLPM_123.45"
+// offset = 0x1a2b00         # Where the first glyph starts
+// count = 96
+// glyph_width = 16
+// glyph_height = 16
+// bits_per_pixel = 4        # 1, 2, 4 or 8
+// msb_first = false         # Whether the leftmost pixel is in the high bits of a byte. PS2 textures put it low.
+// glyph_stride = 128        # Bytes from one glyph to the next, if there's anything between them
+// first_code = 0xa0         # The code of the first glyph, as used in dialog and in .tbl files
+// widths_offset = 0x1a5b00  # A byte per glyph giving its width, if the font has such a table
+// columns = 16              # Glyphs per row of the sheet
+#[derive(Deserialize)]
+struct FontSpec {
+    bits_per_pixel: u8,
+    columns: Option<u32>,
+    count: u32,
+    file: String,
+    #[serde(default)]
+    first_code: u32,
+    glyph_height: u32,
+    glyph_stride: Option<usize>,
+    glyph_width: u32,
+    #[serde(default)]
+    msb_first: bool,
+    offset: usize,
+    widths_offset: Option<usize>,
+}
+
+/// A glyph sheet written by `export_font`, for drawing text with.
+pub struct GlyphSheet {
+    levels: Vec<u16>,
+    spec: FontSpec,
+}
+
+// Keyed by code in hex, like the .tbl files
+#[derive(Serialize, Deserialize)]
+struct WidthTable {
+    widths: BTreeMap<String, u8>,
+}
+
+impl FontSpec {
+    fn code(&self, glyph: u32) -> String {
+        let code = self.first_code + glyph;
+        if code > 0xFF {
+            format!("{code:04X}")
+        } else {
+            format!("{code:02X}")
+        }
+    }
+
+    const fn columns(&self) -> u32 {
+        match self.columns {
+            Some(columns) if columns > 0 => columns,
+            _ => 16,
+        }
+    }
+
+    // Where each glyph's pixels are in the file, after checking they're all there
+    fn glyph_ranges(&self, file_length: usize) -> Result<Vec<(usize, usize)>, io::Error> {
+        let past_the_end = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} glyphs from {:#x} run past the end of {}",
+                    self.count, self.offset, self.file
+                ),
+            )
+        };
+        let pixel_count = (self.glyph_width as usize)
+            .checked_mul(self.glyph_height as usize)
+            .ok_or_else(past_the_end)?;
+        let glyph_size = pixel_count
+            .checked_mul(usize::from(self.bits_per_pixel))
+            .ok_or_else(past_the_end)?
+            .div_ceil(8);
+        let stride = self.glyph_stride.unwrap_or(glyph_size);
+        let ranges = (0..self.count as usize)
+            .map(|glyph| {
+                let start = glyph
+                    .checked_mul(stride)
+                    .and_then(|from_offset| self.offset.checked_add(from_offset))?;
+                let end = start.checked_add(glyph_size)?;
+                (end <= file_length).then_some((start, end))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(past_the_end)?;
+        Ok(ranges)
+    }
+
+    // Which bits of which byte of a glyph hold a pixel
+    fn locate(&self, pixel: usize) -> (usize, u8, u8) {
+        let bits = self.bits_per_pixel;
+        let bit = pixel * usize::from(bits);
+        let within_byte = u8::try_from(bit % 8).unwrap();
+        let shift = if self.msb_first {
+            8 - bits - within_byte
+        } else {
+            within_byte
+        };
+        (bit / 8, shift, u8::MAX >> (8 - bits) << shift)
+    }
+
+    // Where a glyph's pixel goes on the sheet
+    fn sheet_index(&self, glyph: u32, pixel: usize) -> usize {
+        let (sheet_width, _) = self.sheet_size();
+        let glyph_width = self.glyph_width as usize;
+        let x = (glyph % self.columns()) as usize * glyph_width + pixel % glyph_width;
+        let y = (glyph / self.columns() * self.glyph_height) as usize + pixel / glyph_width;
+        y * sheet_width as usize + x
+    }
+
+    fn sheet_size(&self) -> (u32, u32) {
+        let columns = self.columns().min(self.count.max(1));
+        let rows = self.count.div_ceil(columns);
+        (columns * self.glyph_width, rows * self.glyph_height)
+    }
+}
+
+impl GlyphSheet {
+    pub const fn glyph_height(&self) -> u32 {
+        self.spec.glyph_height
+    }
+
+    pub const fn glyph_width(&self) -> u32 {
+        self.spec.glyph_width
+    }
+
+    /// How much ink the glyph for `code` has at a pixel, from 0 to 255. None if the font has no glyph for it.
+    pub fn level(&self, code: u32, x: u32, y: u32) -> Option<u8> {
+        let glyph = code
+            .checked_sub(self.spec.first_code)
+            .filter(|glyph| *glyph < self.spec.count)?;
+        if x >= self.spec.glyph_width || y >= self.spec.glyph_height {
+            return Some(0);
+        }
+        let pixel = (y * self.spec.glyph_width + x) as usize;
+        let level = self.levels.get(self.spec.sheet_index(glyph, pixel))?;
+        Some(u8::try_from(*level).unwrap_or(u8::MAX))
+    }
+}
+
+/// Cuts the glyphs described by a font spec out into a grayscale sheet, along with their widths.
+pub fn export_font(spec_path: &Path, out_dir: &Path) -> Result<(), io::Error> {
+    let (spec, data_path) = load_spec(spec_path)?;
+    let data = fs::read(&data_path)?;
+    let ranges = spec.glyph_ranges(data.len())?;
+    let levels = u16::from(u8::MAX >> (8 - spec.bits_per_pixel));
+    let (sheet_width, sheet_height) = spec.sheet_size();
+    let mut sheet = vec![0; (sheet_width * sheet_height) as usize];
+    let mut measured = BTreeMap::new();
+    for (glyph, (start, end)) in (0..).zip(ranges) {
+        let glyph_data = data.get(start..end).unwrap_or_default();
+        let mut rightmost = 0;
+        for pixel in 0..(spec.glyph_width * spec.glyph_height) as usize {
+            let (byte, shift, mask) = spec.locate(pixel);
+            let level = (glyph_data.get(byte).copied().unwrap_or_default() & mask) >> shift;
+            if level > 0 {
+                rightmost = rightmost.max(pixel % spec.glyph_width as usize + 1);
+            }
+            if let Some(sheet_pixel) = sheet.get_mut(spec.sheet_index(glyph, pixel)) {
+                *sheet_pixel = u8::try_from(u16::from(level) * 255 / levels).unwrap();
+            }
+        }
+        measured.insert(spec.code(glyph), u8::try_from(rightmost).unwrap());
+    }
+
+    // Without a width table in the game, how far each glyph's ink reaches is a decent starting point
+    let widths = match spec.widths_offset {
+        Some(widths_offset) => (0..spec.count)
+            .map(|glyph| {
+                let width = data.get(widths_offset + glyph as usize).copied();
+                width.map(|w| (spec.code(glyph), w)).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("The width table runs past the end of {}", spec.file),
+                    )
+                })
+            })
+            .collect::<Result<BTreeMap<_, _>, _>>()?,
+        None => measured,
+    };
+
+    fs::create_dir_all(out_dir)?;
+    let mut png_encoder = png::Encoder::new(
+        BufWriter::new(File::create(out_dir.join(SHEET_FILE))?),
+        sheet_width,
+        sheet_height,
+    );
+    png_encoder.set_color(ColorType::Grayscale);
+    png_encoder.set_depth(BitDepth::Eight);
+    png_encoder.set_compression(Compression::Fast);
+    let mut pixel_writer = png_encoder.write_header()?;
+    pixel_writer.write_image_data(&sheet)?;
+    pixel_writer.finish()?;
+    write_fonts_fragment(out_dir, &widths)?;
+    let widths_text = toml::to_string(&WidthTable { widths }).map_err(io::Error::other)?;
+    let source = if spec.widths_offset.is_some() {
+        "the font's width table"
+    } else {
+        "measuring each glyph, as the font has no width table to write them back to"
+    };
+    fs::write(
+        out_dir.join(WIDTHS_FILE),
+        format!("# Widths in pixels, by code. Taken from {source}.\n{widths_text}"),
+    )?;
+    info!(
+        "Exported {} glyphs to {}",
+        spec.count,
+        out_dir.join(SHEET_FILE).display()
+    );
+    Ok(())
+}
+
+/// Writes a copy of the file holding the font to `out_path`, with an edited glyph sheet, and widths if the font has a
+/// table for them, written into it.
+pub fn import_font(spec_path: &Path, in_dir: &Path, out_path: &Path) -> Result<(), io::Error> {
+    let (spec, data_path) = load_spec(spec_path)?;
+    if out_path.exists() && fs::canonicalize(out_path)? == fs::canonicalize(&data_path)? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is the file the font is read from. Write the patched copy somewhere else, so the original is \
+                 still around to export from.",
+                out_path.display()
+            ),
+        ));
+    }
+    let mut data = fs::read(&data_path)?;
+    let ranges = spec.glyph_ranges(data.len())?;
+    let levels = u16::from(u8::MAX >> (8 - spec.bits_per_pixel));
+
+    let gray = read_gray_sheet(&in_dir.join(SHEET_FILE), spec.sheet_size())?;
+
+    for (glyph, (start, end)) in (0..).zip(ranges) {
+        let Some(glyph_data) = data.get_mut(start..end) else {
+            continue;
+        };
+        for pixel in 0..(spec.glyph_width * spec.glyph_height) as usize {
+            let (byte, shift, mask) = spec.locate(pixel);
+            let shade = gray
+                .get(spec.sheet_index(glyph, pixel))
+                .copied()
+                .unwrap_or_default();
+            // Rounded to the nearest level the font can show
+            let level = u8::try_from((shade * levels + 127) / 255).unwrap();
+            if let Some(glyph_byte) = glyph_data.get_mut(byte) {
+                *glyph_byte = (*glyph_byte & !mask) | ((level << shift) & mask);
+            }
+        }
+    }
+
+    let widths_path = in_dir.join(WIDTHS_FILE);
+    let width_table =
+        toml::from_str::<WidthTable>(&fs::read_to_string(&widths_path)?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", widths_path.display()),
+            )
+        })?;
+    // Brought up to date with any widths edited since exporting
+    write_fonts_fragment(in_dir, &width_table.widths)?;
+    if let Some(widths_offset) = spec.widths_offset {
+        for glyph in 0..spec.count {
+            if let Some(width) = width_table.widths.get(&spec.code(glyph))
+                && let Some(byte) = data.get_mut(widths_offset + glyph as usize)
+            {
+                *byte = *width;
+            }
+        }
+    }
+
+    fs::write(out_path, data)?;
+    info!(
+        "Imported {} glyphs into a copy of {} at {}",
+        spec.count,
+        data_path.display(),
+        out_path.display()
+    );
+    Ok(())
+}
+
+// The text a glyph stands for in dialog, found the same way dialog gets decoded. None if no table has text for it.
+fn glyph_text(code: u32) -> Option<&'static str> {
+    let bytes = if code > 0xFF {
+        u16::try_from(code).ok()?.to_be_bytes().to_vec()
+    } else {
+        vec![u8::try_from(code).ok()?]
+    };
+    let custom = crate::CHARMAP
+        .get()
+        .and_then(|charmap| charmap.decode(&bytes))
+        .filter(|(length, _)| *length == bytes.len())
+        .map(|(_, text)| text);
+    custom.or_else(|| match *bytes.as_slice() {
+        [b' '] => Some(" "),
+        [byte] if *crate::ENGRISH.get().unwrap() => {
+            byte_to_engrish(byte).or_else(|| byte_to_sjis(byte))
+        }
+        [byte] => byte_to_sjis(byte),
+        [high, low] => word_to_sjis([high, low]),
+        _ => None,
+    })
+}
+
+/// Loads a glyph sheet for drawing, along with the spec it was exported with.
+pub fn load_glyph_sheet(spec_path: &Path, sheet_path: &Path) -> Result<GlyphSheet, io::Error> {
+    let (spec, _) = load_spec(spec_path)?;
+    let levels = read_gray_sheet(sheet_path, spec.sheet_size())?;
+    Ok(GlyphSheet { levels, spec })
+}
+
+fn load_spec(spec_path: &Path) -> Result<(FontSpec, PathBuf), io::Error> {
+    let spec_dir = spec_path.parent().unwrap_or_else(|| Path::new("."));
+    let spec = toml::from_str::<FontSpec>(&fs::read_to_string(spec_path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", spec_path.display()),
+        )
+    })?;
+    if ![1, 2, 4, 8].contains(&spec.bits_per_pixel)
+        || spec.glyph_width == 0
+        || spec.glyph_height == 0
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Glyphs need a nonzero width and height, and 1, 2, 4 or 8 bits per pixel",
+        ));
+    }
+    let data_path = spec_dir.join(&spec.file);
+    Ok((spec, data_path))
+}
+
+// fonts.toml keys widths by the text a glyph stands for rather than its code, so the widths get written out that way
+// too, under the font `lint` and `preview` pick by default
+fn write_fonts_fragment(dir: &Path, widths: &BTreeMap<String, u8>) -> Result<(), io::Error> {
+    let mut by_text = BTreeMap::new();
+    let mut unknown = 0;
+    for (code, width) in widths {
+        match u32::from_str_radix(code, 16).ok().and_then(glyph_text) {
+            Some(text) if text != "\n" => {
+                by_text.insert(text, *width);
+            }
+            _ => unknown += 1,
+        }
+    }
+    let font_name = if *crate::ENGRISH.get().unwrap() {
+        "en"
+    } else {
+        "ja"
+    };
+    let fragment = BTreeMap::from([(font_name, BTreeMap::from([("widths", by_text)]))]);
+    let fragment_text = toml::to_string(&fragment).map_err(io::Error::other)?;
+    fs::write(
+        dir.join(FONTS_FILE),
+        format!(
+            "# The widths from {WIDTHS_FILE}, by text rather than code, to replace the widths of the {font_name} font in a \
+             copy of\n# fonts.toml. {unknown} glyph(s) without text (add them to your --charmap first) are left \
+             out.\n{fragment_text}"
+        ),
+    )
+}
+
+// The gray level of every pixel of a sheet, after checking it's the size the spec calls for
+fn read_gray_sheet(sheet_path: &Path, (width, height): (u32, u32)) -> Result<Vec<u16>, io::Error> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(sheet_path)?));
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let mut png_reader = decoder.read_info()?;
+    let mut buffer = vec![0; png_reader.output_buffer_size().unwrap_or_default()];
+    let frame = png_reader.next_frame(&mut buffer)?;
+    if (frame.width, frame.height) != (width, height) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is {}x{}, but the font needs a {width}x{height} sheet",
+                sheet_path.display(),
+                frame.width,
+                frame.height,
+            ),
+        ));
+    }
+    // Editors like to save as RGB, so the average of the color channels counts as the gray level
+    let channels = frame.color_type.samples();
+    let color_channels = if channels >= 3 { 3 } else { 1 };
+    Ok(buffer
+        .chunks_exact(channels)
+        .take((frame.width * frame.height) as usize)
+        .map(|pixel| {
+            let sum = pixel
+                .iter()
+                .take(color_channels)
+                .map(|channel| u32::from(*channel))
+                .sum::<u32>();
+            u16::try_from(sum / u32::try_from(color_channels).unwrap()).unwrap()
+        })
+        .collect())
+}
@@ -0,0 +1,183 @@
+#![expect(clippy::single_call_fn, reason = "readability")]
+use crate::{
+    lz77_le::decompress,
+    sggg_codec::{SgggImage, png_to_sggg, write_indexed_png},
+};
+use log::{debug, info, warn};
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+struct GalleryEntry {
+    dat_name: String,
+    dimensions: (u32, u32),
+    file_name: String,
+    // Relative to the gallery directory, one per palette
+    thumbnails: Vec<String>,
+}
+
+/// Renders every SGGG image found in an extracted workspace into an HTML page, along with a
+/// thumbnail for each of its alternate palettes.
+pub fn build_gallery(workspace: &Path, out_dir: &Path) -> Result<(), io::Error> {
+    fs::create_dir_all(out_dir)?;
+    let mut dat_dirs = fs::read_dir(workspace)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && path.to_string_lossy().ends_with("DAT"))
+        .collect::<Vec<_>>();
+    dat_dirs.sort();
+
+    let mut entries = Vec::with_capacity(1024);
+    for dat_dir in dat_dirs {
+        let dat_name = dat_dir.file_name().unwrap().to_string_lossy().into_owned();
+        info!("Scanning '{}'", dat_dir.to_string_lossy());
+        let mut slot_files = fs::read_dir(&dat_dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        slot_files.sort();
+        let thumbnail_dir = out_dir.join(&dat_name);
+        for slot_file in slot_files {
+            let Some(image) = load_image(&dat_name, &slot_file)? else {
+                continue;
+            };
+            fs::create_dir_all(&thumbnail_dir)?;
+            let file_name = slot_file
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            let slot = file_name.split('.').next().unwrap_or_default().to_owned();
+            let (width, height) = image.dimensions();
+            let mut thumbnails = Vec::with_capacity(2);
+            let mut palette_index = 0;
+            while let Some(palette) = image.palette(palette_index) {
+                let thumbnail_name = format!("{slot}-p{palette_index}.png");
+                let mut bw = BufWriter::new(File::create(thumbnail_dir.join(&thumbnail_name))?);
+                write_indexed_png(&mut bw, width, height, palette, &[(image.pixels(), 0)])?;
+                thumbnails.push(format!("{dat_name}/{thumbnail_name}"));
+                palette_index += 1;
+            }
+            debug!(
+                "{dat_name}/{file_name}: {width}x{height}, {} palette(s)",
+                thumbnails.len()
+            );
+            entries.push(GalleryEntry {
+                dat_name: dat_name.clone(),
+                dimensions: (width, height),
+                file_name,
+                thumbnails,
+            });
+        }
+    }
+
+    let index_path = out_dir.join("index.html");
+    let mut bw = BufWriter::new(File::create(&index_path)?);
+    bw.write_all(render_html(&entries).as_bytes())?;
+    bw.flush()?;
+    info!(
+        "Wrote {} images to {}",
+        entries.len(),
+        index_path.to_string_lossy()
+    );
+    Ok(())
+}
+
+/// Loads an extracted image, whether it was converted to PNG or copied over as-is with --copy-images.
+pub fn load_image(dat_name: &str, slot_file: &Path) -> Result<Option<SgggImage>, io::Error> {
+    let file_name = slot_file.file_name().unwrap().to_string_lossy();
+    if file_name.contains("eventdialog") || file_name.ends_with("bin") {
+        return Ok(None);
+    }
+    if file_name.ends_with("png") {
+        let mut reader = BufReader::new(File::open(slot_file)?);
+        return match png_to_sggg(&mut reader) {
+            Ok(sggg) => SgggImage::from_sggg(&sggg).map(Some),
+            Err(e) => {
+                warn!("Skipping {}: {e}", slot_file.to_string_lossy());
+                Ok(None)
+            }
+        };
+    }
+    let data = fs::read(slot_file)?;
+    if data.get(10..14) == Some(b"SGGG") {
+        let slot_number = file_name
+            .split('.')
+            .next()
+            .and_then(|slot| slot.parse().ok())
+            .unwrap_or_default();
+        let sggg = decompress(OsStr::new(dat_name), slot_number, data)?;
+        return SgggImage::from_sggg(&sggg).map(Some);
+    }
+    if data.get(0..4) == Some(b"SGGG") {
+        return SgggImage::from_sggg(&data).map(Some);
+    }
+    Ok(None)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(entries: &[GalleryEntry]) -> String {
+    let mut html = String::with_capacity(256 * entries.len());
+    html.push_str(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+        "<title>Aeroprism image gallery</title>\n<style>\n",
+        "body { font-family: sans-serif; background: #333; color: #eee; }\n",
+        "section { display: flex; flex-wrap: wrap; gap: 8px; }\n",
+        ".entry { background: #222; padding: 6px; border-radius: 4px; }\n",
+        ".entry img { max-width: 192px; max-height: 192px; image-rendering: pixelated; ",
+        "background: repeating-conic-gradient(#555 0% 25%, #444 0% 50%) 50% / 16px 16px; }\n",
+        ".entry p { margin: 2px 0; font-size: 12px; }\n",
+        "</style>\n</head>\n<body>\n<h1>Aeroprism image gallery</h1>\n"
+    ));
+    let mut current_dat = None;
+    for entry in entries {
+        if current_dat != Some(&entry.dat_name) {
+            if current_dat.is_some() {
+                html.push_str("</section>\n");
+            }
+            let dat_name = escape_html(&entry.dat_name);
+            html.push_str(format!("<h2 id=\"{dat_name}\">{dat_name}</h2>\n<section>\n").as_str());
+            current_dat = Some(&entry.dat_name);
+        }
+        let (width, height) = entry.dimensions;
+        html.push_str(
+            format!(
+                "<div class=\"entry\">\n<p><b>{}/{}</b></p>\n<p>{width}x{height}, {} palette(s)</p>\n",
+                escape_html(&entry.dat_name),
+                escape_html(&entry.file_name),
+                entry.thumbnails.len()
+            )
+            .as_str(),
+        );
+        for (i, thumbnail) in entry.thumbnails.iter().enumerate() {
+            let label = if i == 0 {
+                "Primary palette".to_owned()
+            } else {
+                format!("Alt palette {}", i - 1)
+            };
+            html.push_str(
+                format!(
+                    "<img src=\"{}\" title=\"{label}\" alt=\"{label}\">\n",
+                    escape_html(thumbnail)
+                )
+                .as_str(),
+            );
+        }
+        html.push_str("</div>\n");
+    }
+    if current_dat.is_some() {
+        html.push_str("</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
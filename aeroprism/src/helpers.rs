@@ -1,5 +1,6 @@
+use crate::lz77_le::decompress;
 use core::{convert, error, fmt, num::ParseIntError};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::{fs, io};
 
 const HEX_BYTES: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
@@ -83,6 +84,50 @@ pub fn hex_edit_encode(bytes: &[u8]) -> String {
         .collect()
 }
 
+// Splits a whole DAT file into its blobs. The header is a blob count, followed by the block offset of each blob and then
+// EOF, all as u32.
+pub fn dat_blobs(dat: &[u8]) -> Vec<&[u8]> {
+    let read_u32 = |pos: usize| {
+        dat.get(pos..pos + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    let blob_count = read_u32(0).unwrap_or_default();
+    (0..blob_count)
+        .filter_map(|i| {
+            let start = read_u32(4 + i * 4)? * crate::DAT_BLOCK_SIZE;
+            let end = read_u32(8 + i * 4)? * crate::DAT_BLOCK_SIZE;
+            dat.get(start..end.min(dat.len()))
+        })
+        .collect()
+}
+
+/// Every blob in a DAT straight from the ISO, decompressed.
+pub fn read_dat_blobs(dat_path: &Path) -> Result<Vec<Vec<u8>>, io::Error> {
+    #[expect(
+        clippy::absolute_paths,
+        reason = "Would conflict with other function calls otherwise."
+    )]
+    let dat = std::fs::read(dat_path)?;
+    let dat_name = dat_path.file_name().unwrap_or_default();
+    (0..)
+        .zip(dat_blobs(&dat))
+        .map(|(slot, blob)| {
+            if blob.starts_with(b"CM") {
+                decompress(dat_name, slot, blob.to_vec())
+            } else {
+                Ok(blob.to_vec())
+            }
+        })
+        .collect()
+}
+
+// Slot files look like 0307.lz77.png, so everything up to the first dot is the slot number
+pub fn slot_sibling(component_file: &Path, extension: &str) -> PathBuf {
+    let file_name = component_file.file_name().unwrap().to_string_lossy();
+    let slot = file_name.split('.').next().unwrap_or_default();
+    component_file.with_file_name(format!("{slot}.{extension}"))
+}
+
 pub async fn copy_dir_all<P: AsRef<Path> + Sync + Send>(src: P, dst: P) -> io::Result<()> {
     fs::create_dir_all(&dst).await?;
     let mut read_dir = fs::read_dir(&src).await.unwrap();
@@ -34,18 +34,49 @@
 #![allow(clippy::integer_division, reason = "will fix these later")]
 #![allow(clippy::single_call_fn, reason = "will fix these later")]
 mod events;
+mod font;
+mod gallery;
 mod helpers;
 mod lz77_le;
+mod maprender;
 mod sggg_codec;
+mod sprites;
 extern crate alloc;
 use crate::{
-    events::{IndexMapWrapper, codec::parse_events, rebuild_event, save_dialog_strings},
-    helpers::copy_dir_all,
+    events::{
+        IndexMapWrapper,
+        bilingual::pair_workspaces,
+        cfg::{GraphFormat, graph_event},
+        charmap::Charmap,
+        check_relocations,
+        codec::parse_events,
+        disasm::list_event,
+        gettext::{export_catalog, import_catalog},
+        keys::{KEYS_FILE, KeyScheme, migrate, warn_if_unrecorded, write_scheme},
+        lint::check_dialog,
+        pointers::survey_dat,
+        reachability::check_events,
+        rebuild_slot,
+        release::{DEFAULT_RELEASES, Profile, RELEASE_FILE, find_workspace, read_recorded, record},
+        relocate::report_relocations,
+        save_dialog_strings,
+        textbox::preview_string,
+        tm::survey_translations,
+        validate::check_workspace,
+    },
+    font::{export_font, import_font},
+    gallery::build_gallery,
+    helpers::{copy_dir_all, dat_blobs},
     lz77_le::{compress_lz77_le, decompress},
-    sggg_codec::{convert_to_png, png_to_sggg},
+    maprender::render_maps,
+    sggg_codec::{convert_to_png, png_to_sggg, source_fingerprint, untouched_source_fingerprint},
+    sprites::{
+        export_frames, frames_path, import_frames, is_sprite_sheet, load_sprite_sheet,
+        sprite_sheet_path,
+    },
 };
-use alloc::collections::BTreeMap;
-use clap::Parser;
+use alloc::{collections::BTreeMap, sync::Arc};
+use clap::{Parser, Subcommand};
 use colog::basic_builder;
 use core::time::Duration;
 use env_logger::Target;
@@ -53,6 +84,7 @@ use log::{Level, LevelFilter, debug, info, log_enabled, trace, warn};
 use shellexpand::path;
 use soft_canonicalize::soft_canonicalize;
 use std::{
+    collections::HashMap,
     ffi::OsStr,
     io::Cursor,
     path::{Path, PathBuf},
@@ -68,42 +100,313 @@ use tokio::{
 };
 
 const DAT_BLOCK_SIZE: usize = 2048;
+static CHARMAP: OnceLock<Charmap> = OnceLock::new();
 static ENGRISH: OnceLock<bool> = OnceLock::new();
+static RELEASE: OnceLock<Profile> = OnceLock::new();
+static STRICT: OnceLock<bool> = OnceLock::new();
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "Each is an independent command line switch"
+)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// When unpacking data, copy over the images rather than decompressing/converting them. This saves time when rebuilding if you aren't going to modify any images.
+    /// A custom character table (Atlas .tbl style, like `80=é`) for a patched font. Its entries go ahead of the built
+    /// in ones when unpacking and repacking dialog.
+    #[arg(long, global = true)]
+    charmap: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// When unpacking data, copy over the images rather than decompressing/converting them. Mostly unnecessary now, as untouched images get reused as-is when rebuilding with `--original` anyways.
     #[arg(short, long)]
     copy_images: bool,
 
-    /// Whether the source files are from an English translation or a Japanese translation
-    #[arg(short, long)]
+    /// Whether the source files are from an English translation or a Japanese translation. Release profiles marked
+    /// `english`, like the one a workspace unpacked with `-e` records, turn this on by themselves.
+    #[arg(short, long, global = true)]
     engrish: bool,
 
     /// The source directory to read from.
     /// When extracting to files, this is the path to the mounted ISO image.
     /// When repacking to an ISO, this is the path to the unpacked (that you can modify) files.
-    in_path: PathBuf,
+    #[arg(required = true)]
+    in_path: Option<PathBuf>,
 
     /// The log level to use. The higher the level, the noisier the output.
-    #[arg(short, long, default_value = "info")]
+    #[arg(short, long, default_value = "info", global = true)]
     log_level: LevelFilter,
 
+    /// When repacking, the mounted ISO the workspace was unpacked from. Images that haven't been edited get their
+    /// original bytes copied out of its DATs rather than being recompressed.
+    #[arg(long)]
+    original: Option<PathBuf>,
+
     /// When extracting, this is where to put the extracted files
     /// When repacking to an ISO, this is where the repacked files go.
     #[arg(short, long, default_value = "./psg2_data")]
     out_path: PathBuf,
 
+    /// Which release the files come from, from the release profiles: `ja` or `en` built in. This decides which bytes
+    /// are symbols like [Musik] rather than characters. Without it, repacking and the commands working on a workspace
+    /// use the release recorded in the workspace's release.toml when it was unpacked; otherwise the SLPM in the input
+    /// folder or workspace picks it if a profile lists its fingerprint, and failing that it's `en` with `-e` and `ja`
+    /// without.
+    #[arg(long, global = true)]
+    release: Option<String>,
+
+    /// A releases.toml with symbol control codes for each release, in place of the built in one.
+    #[arg(long, global = true)]
+    releases: Option<PathBuf>,
+
     /// Whether you're repacking to an ISO or extracting. Defaults to extracting.
     #[arg(short, long)]
     repack: bool,
 
+    /// When repacking, fail instead of carrying on when an event has a pointer to a section it doesn't have, or a raw
+    /// word still holding the old offset of a section that moved. `aeroprism relocations` lists them ahead of time.
+    #[arg(long)]
+    strict: bool,
+
     /// The number of threads to work with. If you're using an HDD, lowering this might help. Minimum value is 1, defaults to the number of CPU cores on your system.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     threads: Option<usize>,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Pair up the dialog of workspaces unpacked from the Japanese and English releases, string by string.
+    Bilingual {
+        /// The workspace unpacked from the Japanese release.
+        ja: PathBuf,
+
+        /// The workspace unpacked from the English release, with `-e`.
+        en: PathBuf,
+
+        /// Where to write the paired up dialog.
+        #[arg(short, long, default_value = "./psg2_bilingual.toml")]
+        out_path: PathBuf,
+    },
+    /// Draw an unpacked event's control flow as a graph, with its dialog on the nodes.
+    Cfg {
+        /// The event slot to draw, with or without its extensions.
+        event: PathBuf,
+
+        /// Graphviz DOT (render with `dot -Tsvg`) or Mermaid (pastes straight into GitHub markdown).
+        #[arg(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+
+        /// Write the graph to a file instead of the console.
+        #[arg(short, long)]
+        out_path: Option<PathBuf>,
+    },
+    /// Print an unpacked event as labeled pseudo-assembly, e.g. `disasm psg2_data/EVENT.DAT/0012`.
+    Disasm {
+        /// The event slot to list, with or without its extensions.
+        event: PathBuf,
+
+        /// Write the listing to a file instead of the console.
+        #[arg(short, long)]
+        out_path: Option<PathBuf>,
+    },
+    /// Cut the glyphs of a font out into a grayscale sheet plus its widths, as described by a font spec file.
+    FontExport {
+        /// The font spec saying where the glyphs are and how they're stored.
+        spec: PathBuf,
+
+        /// Where to put font.png and widths.toml, along with a fonts.toml holding the same widths by text.
+        #[arg(short, long, default_value = "./psg2_font")]
+        out_path: PathBuf,
+    },
+    /// Write a copy of the file the font spec points at, with an edited glyph sheet and widths.toml written into it.
+    FontImport {
+        /// The font spec the sheet was exported with.
+        spec: PathBuf,
+
+        /// The folder holding the edited font.png and widths.toml.
+        #[arg(short, long, default_value = "./psg2_font")]
+        in_path: PathBuf,
+
+        /// Where to write the patched copy, such as the executable in a workspace about to be repacked.
+        #[arg(short, long)]
+        out_path: PathBuf,
+    },
+    /// Render an HTML gallery of every image in an extracted workspace, including its alternate palettes.
+    Gallery {
+        /// The extracted workspace to scan for images.
+        workspace: PathBuf,
+
+        /// Where to put the gallery's index.html and thumbnails.
+        #[arg(short, long, default_value = "./psg2_gallery")]
+        out_path: PathBuf,
+    },
+    /// Report dialog lines too wide for the text box, and boxes with too many lines, across an extracted workspace.
+    Lint {
+        /// The extracted workspace to check.
+        workspace: PathBuf,
+
+        /// Which font from the fonts table to measure with. Defaults to `en` with `-e`, and `ja` otherwise.
+        #[arg(long)]
+        font: Option<String>,
+
+        /// A fonts.toml with box sizes and glyph widths, in place of the built in one.
+        #[arg(long)]
+        fonts: Option<PathBuf>,
+    },
+    /// Update the dialog keys of an unpacked EVENT.DAT (its dialog, event data and listings) after the way keys get picked
+    /// has changed. Needs the EVENT.DAT it was unpacked from.
+    MigrateKeys {
+        /// The unpacked EVENT.DAT folder, e.g. `psg2_data/EVENT.DAT`.
+        workspace: PathBuf,
+
+        /// The EVENT.DAT it was unpacked from, straight from the ISO.
+        dat: PathBuf,
+    },
+    /// Export the dialog of an extracted workspace as a gettext catalog for Weblate, Poedit and the like.
+    PoExport {
+        /// The extracted workspace to take the original text from.
+        workspace: PathBuf,
+
+        /// A workspace extracted from a translated ISO, to fill in the translations from. Without it, the catalog is
+        /// a template with every translation left empty.
+        #[arg(long)]
+        translation: Option<PathBuf>,
+
+        /// Where to write the catalog.
+        #[arg(short, long, default_value = "./psg2_dialog.pot")]
+        out_path: PathBuf,
+    },
+    /// Write the translations from a gettext catalog back into the dialog files of an extracted workspace.
+    /// Untranslated and fuzzy entries are left alone.
+    PoImport {
+        /// The translated catalog.
+        po: PathBuf,
+
+        /// The extracted workspace to write the translations into.
+        workspace: PathBuf,
+    },
+    /// List every word in EVENT.DAT that looks like a pointer but isn't handled as one yet.
+    Pointers {
+        /// The EVENT.DAT to scan, straight from the ISO.
+        dat: PathBuf,
+
+        /// Only list the ones landing right at the start of an instruction or string.
+        #[arg(long)]
+        likely: bool,
+    },
+    /// Render a dialog string to PNG the way the text box shows it, with its portraits, colors and a box per [More].
+    Preview {
+        /// The event slot the string belongs to, with or without its extensions.
+        event: PathBuf,
+
+        /// The string's key in the dialog file, like `01a4`.
+        key: String,
+
+        /// Which font from the fonts table to lay the text out with. Defaults to `en` with `-e`, and `ja` otherwise.
+        #[arg(long)]
+        font: Option<String>,
+
+        /// A fonts.toml with box sizes and glyph widths, in place of the built in one.
+        #[arg(long)]
+        fonts: Option<PathBuf>,
+
+        /// A preview spec giving the glyph sheet to draw with and the image for each portrait.
+        #[arg(long)]
+        spec: Option<PathBuf>,
+
+        /// Where to write the preview.
+        #[arg(short, long, default_value = "./psg2_preview.png")]
+        out_path: PathBuf,
+    },
+    /// Report the sections of unpacked events that never run, and the dialog that never shows up.
+    Reachability {
+        /// Another label the script starts at, for one that something outside the event jumps to. `L_0040` for a single
+        /// event, or `0012:L_0040` when checking the whole folder. Can be given more than once.
+        #[arg(long = "entry")]
+        entries: Vec<String>,
+
+        /// An event slot like `psg2_data/EVENT.DAT/0012`, or the unpacked `EVENT.DAT` folder to check every event.
+        path: PathBuf,
+    },
+    /// Report which sections of each unpacked event move when it's rebuilt, and any references left pointing at where
+    /// they used to be.
+    Relocations {
+        /// The unpacked EVENT.DAT folder, e.g. `psg2_data/EVENT.DAT`.
+        workspace: PathBuf,
+    },
+    /// Render map floors to PNG using their tilesets, as described by a map spec file.
+    RenderMap {
+        /// The map spec describing the tileset, the map data and each floor to draw.
+        spec: PathBuf,
+
+        /// Where to put the rendered floors.
+        #[arg(short, long, default_value = "./psg2_maps")]
+        out_path: PathBuf,
+    },
+    /// Index the Japanese source of every string in a workspace, listing exact and near duplicates and the ones
+    /// translated more than one way. With `--propagate`, copies a translation to untranslated strings with the same
+    /// source.
+    Tm {
+        /// The workspace extracted from the Japanese release.
+        workspace: PathBuf,
+
+        /// The workspace holding the translations, like a copy of the Japanese one with its dialog edited, or one
+        /// extracted from a translated ISO. A string counts as translated once its text differs from the source.
+        #[arg(long)]
+        translation: Option<PathBuf>,
+
+        /// Write each translation to the untranslated strings sharing its source, in the translation workspace.
+        /// Sources translated more than one way are left for you to settle first.
+        #[arg(long, requires = "translation")]
+        propagate: bool,
+
+        /// How alike two different sources have to be, in percent, to be listed as near duplicates.
+        #[arg(long, default_value_t = 85, value_parser = clap::value_parser!(u8).range(1..=100))]
+        similarity: u8,
+    },
+    /// Report every character in the dialog of an extracted workspace that can't be encoded, which would stop a
+    /// repack. With `-e`, also report the ones missing from the English font. Also reports [Select] prompts whose
+    /// choices no longer match what their script branches on.
+    Validate {
+        /// The extracted workspace to check.
+        workspace: PathBuf,
+
+        /// Swap out the characters that have a substitution, like curly quotes for straight ones, and save the dialog.
+        #[arg(long)]
+        fix: bool,
+
+        /// A substitutions.toml to fix characters with, in place of the built in one.
+        #[arg(long)]
+        substitutions: Option<PathBuf>,
+    },
+}
+
+impl Command {
+    // Where to look for the workspace, and with it the release.toml it was unpacked with. Bilingual pairs up two
+    // workspaces of different releases, and pointers reads an ISO, so neither has one to go by.
+    fn workspace(&self) -> Option<&Path> {
+        match self {
+            Self::Bilingual { .. } | Self::Pointers { .. } => None,
+            Self::Cfg { event, .. } | Self::Disasm { event, .. } | Self::Preview { event, .. } => {
+                Some(event)
+            }
+            Self::FontExport { spec, .. } | Self::RenderMap { spec, .. } => Some(spec),
+            Self::FontImport { out_path, .. } => Some(out_path),
+            Self::Gallery { workspace, .. }
+            | Self::Lint { workspace, .. }
+            | Self::MigrateKeys { workspace, .. }
+            | Self::PoExport { workspace, .. }
+            | Self::PoImport { workspace, .. }
+            | Self::Relocations { workspace }
+            | Self::Tm { workspace, .. }
+            | Self::Validate { workspace, .. } => Some(workspace),
+            Self::Reachability { path, .. } => Some(path),
+        }
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
     let mut builder = runtime::Builder::new_multi_thread();
@@ -118,27 +421,220 @@ fn main() {
 async fn main_thread(cli: Cli) -> Result<(), io::Error> {
     // build_iso();
     // return Ok(());
-    ENGRISH.set(cli.engrish).unwrap();
+    STRICT.set(cli.strict).unwrap();
+    if let Some(charmap_path) = &cli.charmap {
+        let charmap = Charmap::parse(&fs::read_to_string(charmap_path).await?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", charmap_path.display()),
+            )
+        })?;
+        CHARMAP.get_or_init(|| charmap);
+    }
     let mut log_builder = basic_builder();
     log_builder.target(Target::Stdout);
     log_builder.filter(None, cli.log_level).init();
     debug!("Debug logging enabled!");
     trace!("Trace logging enabled!");
-    let in_path = soft_canonicalize(path::full(&cli.in_path).unwrap()).unwrap();
-    let out_path = soft_canonicalize(path::full(&cli.out_path).unwrap()).unwrap();
+    let releases_text = match &cli.releases {
+        Some(releases_path) => fs::read_to_string(releases_path).await?,
+        None => DEFAULT_RELEASES.to_owned(),
+    };
+    let mut releases = Profile::parse_table(&releases_text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("releases.toml: {e}")))?;
+    // The workspace a command works on, or the folder being unpacked or repacked
+    let source = match &cli.command {
+        Some(command) => command
+            .workspace()
+            .and_then(|path| find_workspace(&canonical_path(path))),
+        None => cli.in_path.as_deref().map(canonical_path),
+    };
+    // A workspace remembers what it was unpacked with, which beats guessing
+    let recorded = match source
+        .as_deref()
+        .filter(|_| cli.repack || cli.command.is_some())
+    {
+        Some(workspace) => read_recorded(workspace)?,
+        None => None,
+    };
+    let release_name = match (&cli.release, recorded) {
+        (Some(release_name), _) => release_name.clone(),
+        (None, Some(recorded_name)) => {
+            info!("Using the {recorded_name} release the workspace was unpacked with");
+            recorded_name
+        }
+        (None, None) => detect_release(&releases, source.as_deref())
+            .unwrap_or_else(|| if cli.engrish { "en" } else { "ja" }.to_owned()),
+    };
+    let release = releases.remove(&release_name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("No release named {release_name} in releases.toml"),
+        )
+    })?;
+    debug!("Using the {release_name} release profile");
+    ENGRISH.set(cli.engrish || release.is_english()).unwrap();
+    RELEASE.get_or_init(|| release);
+    if let Some(command) = cli.command {
+        return run_command(command);
+    }
+    let in_path = canonical_path(cli.in_path.as_ref().unwrap());
+    let out_path = canonical_path(&cli.out_path);
 
     if cli.repack {
-        walk_build(in_path, out_path).await?;
+        walk_build(in_path, out_path, cli.original).await?;
     } else {
         walk_iso(&in_path, &out_path, cli.copy_images).await?;
+        record(&out_path, &release_name)?;
     }
     Ok(())
 }
 
+// Picks a release by the fingerprint of the SLPM executable in the input folder, which is the ISO when unpacking and
+// the workspace when repacking
+fn detect_release(releases: &HashMap<String, Profile>, in_path: Option<&Path>) -> Option<String> {
+    #[expect(
+        clippy::absolute_paths,
+        reason = "Would conflict with other function calls otherwise."
+    )]
+    let executable = std::fs::read_dir(in_path?)
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|entry| entry.file_name().to_string_lossy().starts_with("SLPM_"))?;
+    #[expect(
+        clippy::absolute_paths,
+        reason = "Would conflict with other function calls otherwise."
+    )]
+    let fingerprint = source_fingerprint(&std::fs::read(executable.path()).ok()?);
+    let detected = releases
+        .iter()
+        .find(|(_, release)| release.matches(&fingerprint))
+        .map(|(name, _)| name.clone());
+    match &detected {
+        Some(name) => info!("Detected the {name} release"),
+        None => info!(
+            "{} has fingerprint {fingerprint}, which no release profile lists. Add it to the release's executables in \
+             releases.toml to have it picked automatically.",
+            executable.file_name().to_string_lossy()
+        ),
+    }
+    detected
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    soft_canonicalize(path::full(path).unwrap()).unwrap()
+}
+
+#[expect(clippy::single_call_fn, reason = "Readability")]
+fn run_command(command: Command) -> Result<(), io::Error> {
+    match command {
+        Command::Bilingual { ja, en, out_path } => pair_workspaces(
+            &canonical_path(&ja),
+            &canonical_path(&en),
+            &canonical_path(&out_path),
+        ),
+        Command::Cfg {
+            event,
+            format,
+            out_path,
+        } => graph_event(
+            &canonical_path(&event),
+            format,
+            out_path.map(|path| canonical_path(&path)).as_deref(),
+        ),
+        Command::Disasm { event, out_path } => list_event(
+            &canonical_path(&event),
+            out_path.map(|path| canonical_path(&path)).as_deref(),
+        ),
+        Command::FontExport { spec, out_path } => {
+            export_font(&canonical_path(&spec), &canonical_path(&out_path))
+        }
+        Command::FontImport {
+            spec,
+            in_path,
+            out_path,
+        } => import_font(
+            &canonical_path(&spec),
+            &canonical_path(&in_path),
+            &canonical_path(&out_path),
+        ),
+        Command::Gallery {
+            workspace,
+            out_path,
+        } => build_gallery(&canonical_path(&workspace), &canonical_path(&out_path)),
+        Command::Lint {
+            workspace,
+            font,
+            fonts,
+        } => check_dialog(
+            &canonical_path(&workspace),
+            font,
+            fonts.map(|path| canonical_path(&path)).as_deref(),
+        ),
+        Command::MigrateKeys { workspace, dat } => {
+            migrate(&canonical_path(&workspace), &canonical_path(&dat))
+        }
+        Command::PoExport {
+            workspace,
+            translation,
+            out_path,
+        } => export_catalog(
+            &canonical_path(&workspace),
+            translation.map(|path| canonical_path(&path)).as_deref(),
+            &canonical_path(&out_path),
+        ),
+        Command::PoImport { po, workspace } => {
+            import_catalog(&canonical_path(&po), &canonical_path(&workspace))
+        }
+        Command::Pointers { dat, likely } => survey_dat(&canonical_path(&dat), likely),
+        Command::Preview {
+            event,
+            key,
+            font,
+            fonts,
+            spec,
+            out_path,
+        } => preview_string(
+            &canonical_path(&event),
+            &key,
+            font,
+            fonts.map(|path| canonical_path(&path)).as_deref(),
+            spec.map(|path| canonical_path(&path)).as_deref(),
+            &canonical_path(&out_path),
+        ),
+        Command::Reachability { entries, path } => check_events(&canonical_path(&path), &entries),
+        Command::Relocations { workspace } => report_relocations(&canonical_path(&workspace)),
+        Command::RenderMap { spec, out_path } => {
+            render_maps(&canonical_path(&spec), &canonical_path(&out_path))
+        }
+        Command::Tm {
+            workspace,
+            translation,
+            propagate,
+            similarity,
+        } => survey_translations(
+            &canonical_path(&workspace),
+            translation.map(|path| canonical_path(&path)).as_deref(),
+            propagate,
+            usize::from(similarity),
+        ),
+        Command::Validate {
+            workspace,
+            fix,
+            substitutions,
+        } => check_workspace(
+            &canonical_path(&workspace),
+            fix,
+            substitutions.map(|path| canonical_path(&path)).as_deref(),
+        ),
+    }
+}
+
 #[expect(clippy::single_call_fn, reason = "Readability")]
 async fn walk_build<P: AsRef<Path> + Sync + Send + Clone>(
     in_dir: P,
     out_dir: PathBuf,
+    original: Option<PathBuf>,
 ) -> Result<(), io::Error> {
     fs::create_dir_all(&out_dir).await?;
     let now = Instant::now();
@@ -146,8 +642,9 @@ async fn walk_build<P: AsRef<Path> + Sync + Send + Clone>(
     let mut tasks = Vec::with_capacity(16);
     while let Some(dir_entry) = read_dir.next_entry().await.unwrap() {
         let od = out_dir.clone();
+        let original_dir = original.clone();
         tasks.push(tokio::spawn(async move {
-            process_dir_entry(od, dir_entry).await
+            process_dir_entry(od, dir_entry, original_dir).await
         }));
     }
     // let files = vec![
@@ -178,7 +675,7 @@ async fn walk_build<P: AsRef<Path> + Sync + Send + Clone>(
     }
     #[expect(clippy::float_arithmetic, reason = "it's only for display")]
     let time = f64::from(u32::try_from(now.elapsed().as_millis()).unwrap()) / 1_000f64;
-    info!("Total time: {time} sec",);
+    info!("Total time: {time} sec");
     Ok(())
 }
 
@@ -186,6 +683,7 @@ async fn walk_build<P: AsRef<Path> + Sync + Send + Clone>(
 async fn process_dir_entry(
     out_dir: PathBuf,
     dir_entry: fs::DirEntry,
+    original_dir: Option<PathBuf>,
 ) -> Result<PathBuf, io::Error> {
     let path = dir_entry.path();
     let dest = out_dir.join(path.file_name().unwrap());
@@ -193,24 +691,28 @@ async fn process_dir_entry(
         info!("Processing '{}'", path.to_string_lossy());
         // Reconstruct DAT files
         if path.to_string_lossy().ends_with("DAT") {
+            warn_if_unrecorded(&path)?;
             let mut dat_size = 0;
             let mut dat_components = BTreeMap::new();
             let mut tasks = Vec::with_capacity(384);
+            let original_dat = match original_dir {
+                Some(original) => original_blobs(&original.join(path.file_name().unwrap())).await?,
+                None => None,
+            };
             let mut read_dir = fs::read_dir(&path).await.unwrap();
             while let Some(subdir_entry) = read_dir.next_entry().await.unwrap() {
                 let component_file = subdir_entry.path();
-                let component_file_str = component_file.to_string_lossy();
-                if component_file_str.contains("eventdialog") || component_file_str.ends_with("bin")
-                {
+                if is_sidecar(&component_file) {
                     continue;
                 }
                 debug!(
                     "Reconstructing block from {}",
                     component_file.to_string_lossy()
                 );
-                tasks.push(tokio::spawn(
-                    async move { reconstitute(component_file).await },
-                ));
+                let original = original_dat.clone();
+                tasks.push(tokio::spawn(async move {
+                    reconstitute(component_file, original).await
+                }));
             }
             for task in tasks {
                 let (component_file, data) = task.await.unwrap().unwrap();
@@ -272,6 +774,7 @@ async fn process_dir_entry(
     } else if path
         .extension()
         .is_some_and(|stem| !stem.to_string_lossy().ends_with("DAT"))
+        && !path.ends_with(RELEASE_FILE)
     {
         info!(
             "Copying '{}' to '{}'",
@@ -298,42 +801,86 @@ async fn process_dir_entry(
     Ok(dest)
 }
 
+// Anything living alongside the DAT slot files that isn't a slot in its own right
+fn is_sidecar(component_file: &Path) -> bool {
+    let component_file_str = component_file.to_string_lossy();
+    component_file.is_dir()
+        || component_file_str.contains("eventdialog")
+        || component_file_str.ends_with(KEYS_FILE)
+        || component_file_str.ends_with("bin")
+        || is_sprite_sheet(component_file)
+}
+
 #[expect(clippy::single_call_fn, reason = "Readability")]
-async fn reconstitute(mut component_file: PathBuf) -> Result<(PathBuf, Vec<u8>), io::Error> {
+async fn reconstitute(
+    mut component_file: PathBuf,
+    original_dat: Option<Arc<[u8]>>,
+) -> Result<(PathBuf, Vec<u8>), io::Error> {
     let mut data =
         Vec::with_capacity(usize::try_from(component_file.metadata().unwrap().len()).unwrap());
     let file = fs::File::open(&*component_file).await.unwrap();
     let mut br = io::BufReader::new(file);
     br.read_to_end(&mut data).await.unwrap();
     br.flush().await.unwrap();
+    let sprite_sheet = load_sprite_sheet(&sprite_sheet_path(&component_file))?;
+    let mut verbatim = false;
     while component_file.as_path().extension().is_some() {
         let extension = component_file.extension().unwrap().to_string_lossy();
         // println!("{}", component_file.to_string_lossy());
         match extension.as_ref() {
             "png" => {
-                #[expect(
-                    clippy::absolute_paths,
-                    reason = "Would conflict with other function calls otherwise."
-                )]
-                let mut reader = std::io::Cursor::new(&data);
-                data = png_to_sggg(&mut reader).unwrap();
+                // Frames are edited separately from the sheet, so the sheet being untouched doesn't tell us anything
+                if sprite_sheet.is_none()
+                    && let Some(dat) = &original_dat
+                    && let Some(source) = untouched_source(&data, &component_file, dat)
+                {
+                    debug!(
+                        "{} is untouched, reusing its original data",
+                        component_file.to_string_lossy()
+                    );
+                    data = source;
+                    verbatim = true;
+                } else {
+                    #[expect(
+                        clippy::absolute_paths,
+                        reason = "Would conflict with other function calls otherwise."
+                    )]
+                    let mut reader = std::io::Cursor::new(&data);
+                    data = png_to_sggg(&mut reader).unwrap();
+                    if let Some(sheet) = &sprite_sheet {
+                        data = import_frames(&data, sheet, &frames_path(&component_file)).map_err(
+                            |e| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!("{}: {e}", component_file.to_string_lossy()),
+                                )
+                            },
+                        )?;
+                    }
+                }
             }
             "lz77" => {
-                data = compress_lz77_le(&data);
+                // Original data is already compressed
+                if !verbatim {
+                    data = compress_lz77_le(&data);
+                }
                 // println!("Recompressed data {}", encode_hex(&data));
             }
             "toml" | "json" | "eventdialog" | "bin" => (),
-            "eventdata" => {
-                let dialog_file_stem = component_file.file_stem().unwrap();
-                let mut dialog_file_path = component_file.parent().unwrap().join(dialog_file_stem);
-                dialog_file_path.add_extension("eventdialog");
-                dialog_file_path.add_extension("toml");
-                data = rebuild_event(
-                    &data,
-                    component_file.to_string_lossy().as_ref(),
-                    dialog_file_path,
-                )
-                .unwrap();
+            // The JSON, or a hand written or disassembled event listing used in place of it
+            "eventdata" | "evs" => {
+                let file_name = component_file.to_string_lossy().into_owned();
+                if extension == "evs" && has_event_data_json(&component_file)? {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "{file_name} is next to an eventdata.json for the same slot, and both would be built into it. Delete one of them."
+                        ),
+                    ));
+                }
+                let (event_data, report) = rebuild_slot(&component_file, &data)?;
+                check_relocations(&report, &file_name, *STRICT.get().unwrap())?;
+                data = event_data;
                 // println!("Rebuilt event: {}", encode_hex(&data));
             }
             _ => {
@@ -355,6 +902,49 @@ async fn reconstitute(mut component_file: PathBuf) -> Result<(PathBuf, Vec<u8>),
     Ok((component_file, data))
 }
 
+// Whether an event listing's slot still has its JSON as well, whatever extensions either was saved with
+fn has_event_data_json(listing: &Path) -> Result<bool, io::Error> {
+    let file_name = listing.file_name().unwrap_or_default().to_string_lossy();
+    let slot_prefix = format!("{}.", file_name.split('.').next().unwrap_or_default());
+    #[expect(
+        clippy::absolute_paths,
+        reason = "Would conflict with other function calls otherwise."
+    )]
+    for entry in std::fs::read_dir(listing.parent().unwrap_or_else(|| Path::new(".")))? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if name.starts_with(&slot_prefix) && name.ends_with("eventdata.json") {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// The original DAT a workspace DAT was unpacked from, for copying untouched images out of. Nothing without one to
+// copy from, in which case every image gets recompressed.
+async fn original_blobs(dat_path: &Path) -> Result<Option<Arc<[u8]>>, io::Error> {
+    if !dat_path.is_file() {
+        warn!(
+            "{} not found, so every image in it will be recompressed",
+            dat_path.to_string_lossy()
+        );
+        return Ok(None);
+    }
+    Ok(Some(Arc::from(fs::read(dat_path).await?)))
+}
+
+// The original data an image was extracted from, provided the image hasn't been edited since
+fn untouched_source(png_data: &[u8], component_file: &Path, dat: &[u8]) -> Option<Vec<u8>> {
+    let stored_fingerprint = untouched_source_fingerprint(png_data).unwrap_or_else(|e| {
+        warn!("{}: {e}", component_file.to_string_lossy());
+        None
+    })?;
+    // Slot files look like 0307.lz77.png, numbered the same as the blobs of the DAT
+    let file_name = component_file.file_name()?.to_string_lossy();
+    let slot = file_name.split('.').next()?.parse::<usize>().ok()?;
+    let source = dat_blobs(dat).get(slot)?.to_vec();
+    (source_fingerprint(&source) == stored_fingerprint).then_some(source)
+}
+
 #[expect(clippy::single_call_fn, reason = "Readability")]
 async fn walk_iso<P: AsRef<Path> + Send + Sync>(
     in_dir: P,
@@ -467,6 +1057,9 @@ async fn unpack_dat<T: AsyncBufReadExt + Unpin, P: AsRef<Path>>(
     // Create the directory if we haven't already
     let save_path = PathBuf::with_capacity(128).join(out_dir).join(dat_name);
     create_dir_all(&save_path).await?;
+    if dat_name.to_string_lossy().contains("EVENT") {
+        write_scheme(&save_path, KeyScheme::CURRENT)?;
+    }
 
     // Create a peakable iterator so that we can calculate each blob size as we read each offset
     let mut offsets_iter = block_offsets.into_iter().peekable();
@@ -490,6 +1083,7 @@ async fn unpack_dat<T: AsyncBufReadExt + Unpin, P: AsRef<Path>>(
         dat_reader.read_exact(&mut data).await?;
 
         let mut extensions = Vec::with_capacity(3);
+        let source = data.clone();
 
         if copy_images && data.iter().skip(10).take(4).copied().collect::<Vec<_>>() == b"SGGG" {
             // Just store the data file. No need to do anything else.
@@ -502,7 +1096,11 @@ async fn unpack_dat<T: AsyncBufReadExt + Unpin, P: AsRef<Path>>(
             #[expect(clippy::indexing_slicing, reason = "more concise way to check magic")]
             if data[0..4] == *b"SGGG" {
                 extensions.push("png");
-                data = convert_to_png(data)?;
+                let slot_path = save_path.join(&stem_name);
+                if let Some(sheet) = load_sprite_sheet(&sprite_sheet_path(&slot_path))? {
+                    export_frames(&data, &sheet, &frames_path(&slot_path))?;
+                }
+                data = convert_to_png(data, &source)?;
             } else if dat_name.to_string_lossy().contains("EVENT") {
                 if log_enabled!(Level::Debug) {
                     debug!(
@@ -515,10 +1113,11 @@ async fn unpack_dat<T: AsyncBufReadExt + Unpin, P: AsRef<Path>>(
                 let (ordered_data, dialog_items) =
                     parse_events(&mut event_reader, u32::try_from(data.len()).unwrap())?;
 
-                let dialog_file = save_path.clone().join(format!(
-                    "{stem_name}.{}.eventdialog.toml",
-                    extensions.join(".")
-                ));
+                // Named to match what reconstitute looks for, e.g. 0012.lz77.eventdialog.toml or 0012.eventdialog.toml
+                let mut dialog_stem_parts = vec![stem_name.as_str()];
+                dialog_stem_parts.extend(&extensions);
+                let dialog_stem = dialog_stem_parts.join(".");
+                let dialog_file = save_path.join(format!("{dialog_stem}.eventdialog.toml"));
                 // Save the event dialog separately, and only if it has any data
                 if !dialog_items.is_empty() {
                     save_dialog_strings(&dialog_file, &IndexMapWrapper(dialog_items))?;
@@ -531,7 +1130,10 @@ async fn unpack_dat<T: AsyncBufReadExt + Unpin, P: AsRef<Path>>(
             }
         }
 
-        let leaf_name = format!("{stem_name}.{}", extensions.join("."));
+        // Uncompressed blobs that aren't anything in particular get no extensions at all, rather than a trailing dot
+        let mut leaf_parts = vec![stem_name.as_str()];
+        leaf_parts.extend(&extensions);
+        let leaf_name = leaf_parts.join(".");
         let main_save_path = save_path.clone().join(leaf_name);
 
         let component_file = OpenOptions::new()
@@ -0,0 +1,296 @@
+#![expect(clippy::single_call_fn, reason = "readability")]
+use crate::{gallery::load_image, sggg_codec::rgba_palette};
+use log::info;
+use png::{BitDepth, ColorType, Compression};
+use serde::Deserialize;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::Path,
+};
+
+const OVERLAY_CHEST: [u8; 4] = [0xFF, 0xD7, 0x00, 0xFF];
+const OVERLAY_EXIT: [u8; 4] = [0x00, 0xE0, 0x40, 0xFF];
+const OVERLAY_NPC: [u8; 4] = [0xFF, 0x00, 0xFF, 0xFF];
+
+// Where in a map blob the tile layout lives isn't known yet, so everything needed to draw a map is spelled out in a
+// spec file rather than guessed at. Paths are relative to the spec file. Example:
+//
+// tileset = "MAPDATA.DAT/0013.lz77.png"
+// map = "MAPDATA.DAT/0012.lz77"
+// tile_width = 16
+// tile_height = 16
+//
+// [[floor]]
+// name = "shure_1f"
+// offset = 0x40
+// width = 64
+//
+// [[floor.overlay]]
+// kind = "chest"
+// x = 3
+// y = 5
+#[derive(Deserialize)]
+struct MapSpec {
+    #[serde(rename = "floor")]
+    floors: Vec<Floor>,
+    map: String,
+    // 0 is the primary palette, anything after that is one of the alternates
+    #[serde(default)]
+    palette: usize,
+    tile_height: u32,
+    tile_width: u32,
+    tileset: String,
+}
+
+#[derive(Deserialize)]
+struct Floor {
+    // In tiles, the rest of the map blob if left out
+    height: Option<u32>,
+    name: String,
+    // Where in the map blob the tile indices begin
+    #[serde(default)]
+    offset: usize,
+    #[serde(default, rename = "overlay")]
+    overlays: Vec<Overlay>,
+    #[serde(default)]
+    tile_format: TileFormat,
+    // In tiles
+    width: u32,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum TileFormat {
+    #[default]
+    U16,
+    U8,
+}
+
+#[derive(Deserialize)]
+struct Overlay {
+    kind: OverlayKind,
+    x: u32,
+    y: u32,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum OverlayKind {
+    Chest,
+    Exit,
+    Npc,
+}
+
+impl OverlayKind {
+    const fn color(self) -> [u8; 4] {
+        match self {
+            Self::Chest => OVERLAY_CHEST,
+            Self::Exit => OVERLAY_EXIT,
+            Self::Npc => OVERLAY_NPC,
+        }
+    }
+}
+
+/// Draws every floor described in a map spec to its own PNG in `out_dir`.
+pub fn render_maps(spec_path: &Path, out_dir: &Path) -> Result<(), io::Error> {
+    let spec_dir = spec_path.parent().unwrap_or_else(|| Path::new("."));
+    let spec = toml::from_str::<MapSpec>(&fs::read_to_string(spec_path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", spec_path.display()),
+        )
+    })?;
+    if spec.tile_width == 0
+        || spec.tile_height == 0
+        || spec
+            .floors
+            .iter()
+            .any(|f| f.width == 0 || f.height == Some(0))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Tiles and floors need a nonzero width and height",
+        ));
+    }
+
+    let tileset_path = spec_dir.join(&spec.tileset);
+    let dat_name = tileset_path
+        .parent()
+        .and_then(Path::file_name)
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let tileset = load_image(&dat_name, &tileset_path)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} isn't an image", tileset_path.display()),
+        )
+    })?;
+    let palette = rgba_palette(tileset.palette(spec.palette).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} doesn't have a palette {}",
+                tileset_path.display(),
+                spec.palette
+            ),
+        )
+    })?);
+
+    let map_path = spec_dir.join(&spec.map);
+    let map_data = fs::read(&map_path)?;
+
+    fs::create_dir_all(out_dir)?;
+    for floor in &spec.floors {
+        let layout = map_data.get(floor.offset..).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is only {} bytes long, so {} can't start at {:#x}",
+                    map_path.display(),
+                    map_data.len(),
+                    floor.name,
+                    floor.offset
+                ),
+            )
+        })?;
+        let mut tiles = read_tiles(layout, floor.tile_format);
+        if let Some(height) = floor.height {
+            tiles.truncate((floor.width as usize).saturating_mul(height as usize));
+        }
+        let rows = u32::try_from(tiles.len().div_ceil(floor.width as usize))
+            .ok()
+            .ok_or_else(|| too_big(&floor.name))?;
+        let width = floor
+            .width
+            .checked_mul(spec.tile_width)
+            .ok_or_else(|| too_big(&floor.name))?;
+        let height = rows
+            .checked_mul(spec.tile_height)
+            .ok_or_else(|| too_big(&floor.name))?;
+        let pixel_count = (width as usize)
+            .checked_mul(height as usize)
+            .ok_or_else(|| too_big(&floor.name))?;
+        let mut canvas = vec![[0; 4]; pixel_count];
+
+        let (tileset_width, tileset_height) = tileset.dimensions();
+        let tileset_columns = tileset_width / spec.tile_width;
+        let tileset_tiles = tileset_columns * (tileset_height / spec.tile_height);
+        for (i, &tile) in (0..).zip(&tiles) {
+            // Tiles past the end of the tileset are left transparent
+            if tile >= tileset_tiles {
+                continue;
+            }
+            let tile_pixels = tileset.crop(
+                (tile % tileset_columns) * spec.tile_width,
+                (tile / tileset_columns) * spec.tile_height,
+                spec.tile_width,
+                spec.tile_height,
+            );
+            let (x, y) = (
+                (i % floor.width) * spec.tile_width,
+                (i / floor.width) * spec.tile_height,
+            );
+            for (row_num, row) in (0..).zip(tile_pixels.chunks_exact(spec.tile_width as usize)) {
+                // In usize, as a canvas can hold more pixels than a u32 counts
+                let start = (y + row_num) as usize * width as usize + x as usize;
+                for (canvas_pixel, &index) in canvas.iter_mut().skip(start).zip(row) {
+                    *canvas_pixel = palette.get(index as usize).copied().unwrap_or_default();
+                }
+            }
+        }
+
+        for overlay in &floor.overlays {
+            // Both are whole tiles in from the edge, so the box always fits
+            let (x, y) = overlay
+                .x
+                .checked_mul(spec.tile_width)
+                .zip(overlay.y.checked_mul(spec.tile_height))
+                .filter(|&(x, y)| x < width && y < height)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Overlay at {},{} is outside of the {}x{} tiles of {}",
+                            overlay.x, overlay.y, floor.width, rows, floor.name
+                        ),
+                    )
+                })?;
+            draw_box(
+                &mut canvas,
+                width,
+                x,
+                y,
+                (spec.tile_width, spec.tile_height),
+                overlay.kind.color(),
+            );
+        }
+
+        let out_file = out_dir.join(format!("{}.png", floor.name));
+        write_rgba_png(&out_file, width, height, &canvas)?;
+        info!(
+            "Rendered {}x{} tiles to {}",
+            floor.width,
+            rows,
+            out_file.display()
+        );
+    }
+    Ok(())
+}
+
+fn draw_box(
+    canvas: &mut [[u8; 4]],
+    canvas_width: u32,
+    x: u32,
+    y: u32,
+    (width, height): (u32, u32),
+    color: [u8; 4],
+) {
+    for row in y..y + height {
+        for column in x..x + width {
+            let is_edge =
+                row == y || row == y + height - 1 || column == x || column == x + width - 1;
+            if is_edge
+                && column < canvas_width
+                && let Some(pixel) =
+                    canvas.get_mut(row as usize * canvas_width as usize + column as usize)
+            {
+                *pixel = color;
+            }
+        }
+    }
+}
+
+fn too_big(floor_name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{floor_name} is too big to draw"),
+    )
+}
+
+fn read_tiles(data: &[u8], tile_format: TileFormat) -> Vec<u32> {
+    match tile_format {
+        TileFormat::U8 => data.iter().map(|&tile| u32::from(tile)).collect(),
+        TileFormat::U16 => data
+            .chunks_exact(2)
+            .map(|tile| u32::from(u16::from_le_bytes(tile.try_into().unwrap())))
+            .collect(),
+    }
+}
+
+pub fn write_rgba_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    canvas: &[[u8; 4]],
+) -> Result<(), io::Error> {
+    let mut png_encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    png_encoder.set_color(ColorType::Rgba);
+    png_encoder.set_depth(BitDepth::Eight);
+    png_encoder.set_compression(Compression::Fast);
+    let mut pixel_writer = png_encoder.write_header()?;
+    pixel_writer.write_image_data(canvas.as_flattened())?;
+    pixel_writer.finish()?;
+    Ok(())
+}
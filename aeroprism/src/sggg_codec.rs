@@ -17,9 +17,124 @@ const ALPHA_CHANNEL: usize = 3; // Alpha channel number
 const PALETTE_COLOR_COUNT: usize = 256; // The palette contains 256 color entries total
 const SGGG_HEADER_SIZE: usize = 16;
 
+/// An SGGG image with its pixel rows stitched back together, ready to be cut up or pasted into.
+// Palettes are kept exactly as SGGG stores them (twiddled, PS2 alpha)
+pub struct SgggImage {
+    alt_palettes: Vec<Vec<[u8; CHANNELS_PER_COLOR]>>,
+    header4: [u8; 4],
+    height: u32,
+    palette: Vec<[u8; CHANNELS_PER_COLOR]>,
+    pixels: Vec<u8>,
+    width: u32,
+}
+
+impl SgggImage {
+    pub const fn contains(&self, x: u32, y: u32, width: u32, height: u32) -> bool {
+        matches!(x.checked_add(width), Some(right) if right <= self.width)
+            && matches!(y.checked_add(height), Some(bottom) if bottom <= self.height)
+    }
+
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for row in self
+            .pixels
+            .chunks_exact(self.width as usize)
+            .skip(y as usize)
+            .take(height as usize)
+        {
+            pixels.extend(row.iter().skip(x as usize).take(width as usize));
+        }
+        pixels
+    }
+
+    pub const fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn from_sggg(data: &[u8]) -> Result<Self, io::Error> {
+        let reader = &mut Cursor::new(data);
+        let current_u16 = &mut [0u8; 2];
+        let current_u32 = &mut [0u8; 4];
+        let (width, height, header4) = parse_sggg_header(reader, current_u16, current_u32)?;
+        let palette = read_sggg_palette(reader, current_u32)?;
+        let pixels = sggg_pixels_to_png(reader, width, height)?;
+        let mut alt_palettes = Vec::new();
+        while let Ok(alt_palette) = read_sggg_palette(reader, current_u32) {
+            alt_palettes.push(alt_palette);
+        }
+        Ok(Self {
+            alt_palettes,
+            header4,
+            height,
+            palette,
+            pixels,
+            width,
+        })
+    }
+
+    pub fn into_sggg(self) -> Vec<u8> {
+        let mut sggg = Vec::with_capacity(
+            SGGG_HEADER_SIZE
+                + (PALETTE_COLOR_COUNT * CHANNELS_PER_COLOR * (1 + self.alt_palettes.len()))
+                + self.pixels.len(),
+        );
+
+        // First build the header
+        sggg.extend(*b"SGGG");
+        sggg.extend([1, 0, 0, 0]);
+        let width_u16: u16 = self.width.try_into().unwrap();
+        let height_u16: u16 = self.height.try_into().unwrap();
+        sggg.extend(width_u16.to_le_bytes());
+        sggg.extend(height_u16.to_le_bytes());
+        // Now for that unknown fourth field...
+        sggg.extend(self.header4);
+
+        // Now for the palette and pixel data. Anything past the 512th pixel of a row goes after the rest of the image.
+        sggg.extend(self.palette.into_iter().flatten());
+        let width = self.width as usize;
+        for row in self.pixels.chunks_exact(width) {
+            sggg.extend(row.iter().take(512));
+        }
+        for row in self.pixels.chunks_exact(width) {
+            sggg.extend(row.iter().skip(512));
+        }
+        for alt_palette in self.alt_palettes {
+            sggg.extend(alt_palette.as_flattened());
+        }
+        sggg
+    }
+
+    /// Palette zero is the primary palette, anything after that is an alternate palette.
+    pub fn palette(&self, index: usize) -> Option<&[[u8; CHANNELS_PER_COLOR]]> {
+        if index == 0 {
+            Some(&self.palette)
+        } else {
+            self.alt_palettes.get(index - 1).map(Vec::as_slice)
+        }
+    }
+
+    pub fn paste(&mut self, x: u32, y: u32, width: u32, pixels: &[u8]) {
+        for (row, source_row) in self
+            .pixels
+            .chunks_exact_mut(self.width as usize)
+            .skip(y as usize)
+            .zip(pixels.chunks_exact(width as usize))
+        {
+            for (pixel, source_pixel) in row.iter_mut().skip(x as usize).zip(source_row) {
+                *pixel = *source_pixel;
+            }
+        }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
 pub fn sggg_to_png<R: BufRead + Seek, W: Write>(
     reader: &mut R,
     writer: &mut W,
+    source: Option<&[u8]>,
 ) -> Result<(), io::Error> {
     // Scratchpad for the reader
     let current_u16 = &mut [0u8; 2];
@@ -50,11 +165,13 @@ pub fn sggg_to_png<R: BufRead + Seek, W: Write>(
     // From here, let's just let the png encoder library do most of the heavy lifting...
     let mut png_encoder = png::Encoder::new(writer, width, height);
 
+    let mut fingerprint_palette = None;
     let color_type = if unique_colors.len() == 1 {
         // Grayscale image
         ColorType::Grayscale
     } else {
         let png_palette = sggg_palette_to_png(palette);
+        fingerprint_palette = Some(png_palette.clone());
         // Generate an alpha palette that has a first element as zero, followed by fully opaque 0xFF for everything else.
         png_encoder.set_palette(png_palette);
         png_encoder.set_trns(&alpha_bits);
@@ -68,21 +185,44 @@ pub fn sggg_to_png<R: BufRead + Seek, W: Write>(
     let pngpixels = sggg_pixels_to_png(reader, width, height)?;
 
     // If the 4th field in the header is nonzero, let's store it in the PNG for later reconstitution
-    if u32::from_le_bytes(unknown_data) > 0 {
-        png_encoder.add_text_chunk("Header4".into(), encode_hex(&unknown_data))?;
-    }
+    let header4 = if u32::from_le_bytes(unknown_data) > 0 {
+        let header4_hex = encode_hex(&unknown_data);
+        png_encoder.add_text_chunk("Header4".into(), header4_hex.clone())?;
+        Some(header4_hex)
+    } else {
+        None
+    };
     png_encoder.add_text_chunk("PaletteMeowhash".into(), encode_hex(&palette_hash))?;
 
     // SGGG appears to occasionally store additional palettes, likely for a palette swap.
     // PNG has a similar feature that we can use to store these, namely sPLT chunks.
     // However the library I'm using doesn't support those. So let's just drop them into compressed text fields for now.
     let mut num = 0;
+    let mut alt_palettes = Vec::new();
     while let Ok(sggg_palette) = read_sggg_palette(reader, current_u32) {
         let png_palette = sggg_palette_to_png(sggg_palette);
-        png_encoder.add_ztxt_chunk(format!("AltPalette{num}"), encode_hex(&png_palette))?;
+        let key = format!("AltPalette{num}");
+        let png_palette_hex = encode_hex(&png_palette);
+        png_encoder.add_ztxt_chunk(key.clone(), png_palette_hex.clone())?;
+        alt_palettes.push((key, png_palette_hex));
         num += 1;
     }
 
+    // Remember exactly what we wrote, along with where it came from. If neither changes by the time we repack, the original bytes can be reused as-is.
+    png_encoder.add_text_chunk(
+        "PixelMeowhash".into(),
+        pixel_fingerprint(
+            (width, height),
+            fingerprint_palette.as_deref(),
+            &pngpixels,
+            header4.as_deref(),
+            alt_palettes,
+        ),
+    )?;
+    if let Some(source_data) = source {
+        png_encoder.add_text_chunk("SourceMeowhash".into(), source_fingerprint(source_data))?;
+    }
+
     let mut pixel_writer = png_encoder.write_header()?;
     pixel_writer.write_image_data(&pngpixels)?;
     pixel_writer.finish()?;
@@ -197,8 +337,7 @@ pub fn png_to_sggg<R: BufRead + Seek>(reader: &mut R) -> Result<Vec<u8>, String>
 
     let mut row_num = 0;
     let pixel_row = &mut vec![0; width.try_into().unwrap()];
-    let mut sggg_pixels: Vec<u8> = Vec::with_capacity((width * height) as usize);
-    let mut sggg_pixels_extended: Vec<u8> = Vec::with_capacity((width * height) as usize);
+    let mut pixels: Vec<u8> = Vec::with_capacity((width * height) as usize);
     while let Some(interlace_info) = png_reader
         .read_row(pixel_row)
         .map_err(|e| format!("Error reading PNG row {row_num}: {e}"))?
@@ -217,36 +356,64 @@ pub fn png_to_sggg<R: BufRead + Seek>(reader: &mut R) -> Result<Vec<u8>, String>
                 );
             }
         }
-        sggg_pixels.extend(pixel_row.iter().take(512));
-        sggg_pixels_extended.extend(pixel_row.iter().skip(512));
+        pixels.extend(pixel_row.iter());
 
         row_num += 1;
     }
 
-    // Now to build the SGGG file
-    let mut sggg = Vec::with_capacity(
-        SGGG_HEADER_SIZE + (PALETTE_COLOR_COUNT * CHANNELS_PER_COLOR) + (width * height) as usize,
-    );
+    Ok(SgggImage {
+        alt_palettes: alternative_palettes,
+        header4: unknown_field,
+        height,
+        palette,
+        pixels,
+        width,
+    }
+    .into_sggg())
+}
 
-    // First build the header
-    sggg.extend(*b"SGGG");
-    sggg.extend([1, 0, 0, 0]);
-    let width_u16: u16 = width.try_into().unwrap();
-    let height_u16: u16 = height.try_into().unwrap();
-    sggg.extend(width_u16.to_le_bytes());
-    sggg.extend(height_u16.to_le_bytes());
-    // Now for that unknown fourth field...
-    sggg.extend(unknown_field);
-
-    // Now for the palette and pixel data
-    sggg.extend(palette.into_iter().flatten());
-    sggg.extend(sggg_pixels);
-    sggg.extend(sggg_pixels_extended);
-    for alt_palette in alternative_palettes {
-        sggg.extend(alt_palette.as_flattened());
+/// Writes plain indexed pixels as a PNG without any of the metadata needed to rebuild an SGGG.
+/// More than one frame produces an APNG, with each frame's delay given in milliseconds.
+pub fn write_indexed_png<W: Write>(
+    writer: &mut W,
+    width: u32,
+    height: u32,
+    palette: &[[u8; CHANNELS_PER_COLOR]],
+    frames: &[(&[u8], u16)],
+) -> Result<(), io::Error> {
+    let mut unique_colors = HashSet::with_capacity(256);
+    for color in palette {
+        unique_colors.insert([
+            color[RED_CHANNEL],
+            color[GREEN_CHANNEL],
+            color[BLUE_CHANNEL],
+        ]);
     }
+    let mut alpha_bits = [0xFF; PALETTE_COLOR_COUNT];
+    alpha_bits[0] = 0;
 
-    Ok(sggg)
+    let mut png_encoder = png::Encoder::new(writer, width, height);
+    if unique_colors.len() == 1 {
+        png_encoder.set_color(ColorType::Grayscale);
+    } else {
+        png_encoder.set_palette(sggg_palette_to_png(palette.to_vec()));
+        png_encoder.set_trns(alpha_bits.as_slice());
+        png_encoder.set_color(ColorType::Indexed);
+    }
+    png_encoder.set_depth(BitDepth::Eight);
+    png_encoder.set_compression(Compression::Fast);
+    if frames.len() > 1 {
+        png_encoder.set_animated(u32::try_from(frames.len()).unwrap(), 0)?;
+    }
+    let mut pixel_writer = png_encoder.write_header()?;
+    for (pixels, delay_ms) in frames {
+        if frames.len() > 1 {
+            pixel_writer.set_frame_delay(*delay_ms, 1000)?;
+        }
+        pixel_writer.write_image_data(pixels)?;
+    }
+    pixel_writer.finish()?;
+    Ok(())
 }
 
 fn parse_sggg_header<R: BufRead + Seek>(
@@ -319,6 +486,18 @@ fn sggg_pixels_to_png<R: BufRead + Seek>(
     Ok(pixel_rows.into_iter().flatten().collect::<Vec<_>>())
 }
 
+/// An SGGG palette in plain color order as RGBA, with the first color transparent and everything else opaque, same as
+/// the PNGs we write out.
+pub fn rgba_palette(sggg_palette: &[[u8; CHANNELS_PER_COLOR]]) -> Vec<[u8; CHANNELS_PER_COLOR]> {
+    let mut palette = sggg_palette.to_vec();
+    twiddle_palette(&mut palette);
+    palette
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, color)| color[ALPHA_CHANNEL] = if i == 0 { 0 } else { 0xFF });
+    palette
+}
+
 fn sggg_palette_to_png(mut palette: Vec<[u8; 4]>) -> Vec<u8> {
     // Prepare an SGGG palette for use in a PNG
     twiddle_palette(&mut palette);
@@ -385,14 +564,96 @@ fn twiddle_palette(palette: &mut Vec<[u8; 4]>) {
         });
 }
 
+// Hashes everything png_to_sggg would read back out of the PNG, as it appears in the PNG.
+fn pixel_fingerprint(
+    (width, height): (u32, u32),
+    palette: Option<&[u8]>,
+    pixels: &[u8],
+    header4: Option<&str>,
+    mut alt_palettes: Vec<(String, String)>,
+) -> String {
+    alt_palettes.sort_unstable();
+    let mut fingerprint = Vec::with_capacity(pixels.len() + 1024);
+    fingerprint.extend(width.to_le_bytes());
+    fingerprint.extend(height.to_le_bytes());
+    fingerprint.extend(palette.unwrap_or_default());
+    fingerprint.extend(pixels);
+    fingerprint.extend(header4.unwrap_or_default().as_bytes());
+    for (key, palette_hex) in alt_palettes {
+        fingerprint.extend(key.as_bytes());
+        fingerprint.extend(palette_hex.as_bytes());
+    }
+    encode_hex(&meowhash::MeowHasher::hash(&fingerprint).into_bytes())
+}
+
+pub fn source_fingerprint(source: &[u8]) -> String {
+    encode_hex(&meowhash::MeowHasher::hash(source).into_bytes())
+}
+
+/// If the pixels, palettes and header of a PNG are exactly as they were when we extracted it,
+/// returns the fingerprint of the original blob it was extracted from.
+pub fn untouched_source_fingerprint(png_data: &[u8]) -> Result<Option<String>, String> {
+    let mut png_reader = png::Decoder::new(Cursor::new(png_data))
+        .read_info()
+        .map_err(|e| format!("Error reading PNG info: {e}"))?;
+    let info = png_reader.info();
+    if info.bit_depth != BitDepth::Eight
+        || !matches!(info.color_type, ColorType::Grayscale | ColorType::Indexed)
+    {
+        return Ok(None);
+    }
+    let dimensions = (info.width, info.height);
+    let palette = match info.color_type {
+        ColorType::Indexed => info.palette.as_deref().map(<[u8]>::to_vec),
+        _ => None,
+    };
+    let mut header4 = None;
+    let mut pixel_text = None;
+    let mut source_text = None;
+    for text_chunk in &info.uncompressed_latin1_text {
+        match text_chunk.keyword.as_str() {
+            "Header4" => header4 = Some(text_chunk.text.clone()),
+            "PixelMeowhash" => pixel_text = Some(text_chunk.text.clone()),
+            "SourceMeowhash" => source_text = Some(text_chunk.text.clone()),
+            _ => {
+                // no action needed
+            }
+        }
+    }
+    let (Some(stored_pixel_fingerprint), Some(stored_source_fingerprint)) =
+        (pixel_text, source_text)
+    else {
+        return Ok(None);
+    };
+    let mut alt_palettes = Vec::with_capacity(info.compressed_latin1_text.len());
+    for txt in &info.compressed_latin1_text {
+        if txt.keyword.starts_with("AltPalette") {
+            alt_palettes.push((txt.keyword.clone(), txt.get_text().unwrap()));
+        }
+    }
+
+    let mut pixels = vec![0; png_reader.output_buffer_size().unwrap()];
+    png_reader
+        .next_frame(&mut pixels)
+        .map_err(|e| format!("Error reading PNG pixels: {e}"))?;
+    let pixel_fingerprint = pixel_fingerprint(
+        dimensions,
+        palette.as_deref(),
+        &pixels,
+        header4.as_deref(),
+        alt_palettes,
+    );
+    Ok((pixel_fingerprint == stored_pixel_fingerprint).then_some(stored_source_fingerprint))
+}
+
 #[expect(clippy::single_call_fn, reason = "Readability")]
-pub fn convert_to_png(data: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+pub fn convert_to_png(data: Vec<u8>, source: &[u8]) -> Result<Vec<u8>, io::Error> {
     // Reference? https://en.wikipedia.org/wiki/Segagaga
     // This file format seems most appropriate as a png rather than bmp.
     // Harder to screw up, readily translates, has an alpha channel, can store extra data that we need
     let mut pngwriter = Cursor::new(vec![0; data.len()]);
     let sggg_reader = &mut Cursor::new(data);
-    sggg_to_png(sggg_reader, &mut pngwriter)?;
+    sggg_to_png(sggg_reader, &mut pngwriter, Some(source))?;
     Ok(pngwriter.into_inner())
 }
 
@@ -0,0 +1,204 @@
+#![expect(clippy::single_call_fn, reason = "readability")]
+use crate::{
+    helpers::slot_sibling,
+    sggg_codec::{SgggImage, write_indexed_png},
+};
+use log::{debug, info};
+use serde::Deserialize;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+const SPRITE_SHEET_EXTENSION: &str = "sprites.toml";
+const FRAMES_EXTENSION: &str = "frames";
+const PREVIEW_NAME: &str = "preview.png";
+const DEFAULT_DELAY_MS: u16 = 100;
+
+// Sidecar describing where each frame lives on a sprite sheet. Example:
+//
+// delay_ms = 120
+//
+// [[frame]]
+// x = 0
+// y = 0
+// width = 64
+// height = 96
+//
+// [[frame]]
+// x = 64
+// y = 0
+// width = 64
+// height = 96
+// delay_ms = 250
+#[derive(Deserialize)]
+pub struct SpriteSheet {
+    #[serde(default = "default_delay")]
+    delay_ms: u16,
+    #[serde(rename = "frame")]
+    frames: Vec<Frame>,
+}
+
+#[derive(Deserialize)]
+struct Frame {
+    delay_ms: Option<u16>,
+    height: u32,
+    width: u32,
+    x: u32,
+    y: u32,
+}
+
+impl SpriteSheet {
+    fn check_bounds(&self, image: &SgggImage) -> Result<(), String> {
+        for (i, frame) in self.frames.iter().enumerate() {
+            if frame.width == 0 || frame.height == 0 {
+                return Err(format!(
+                    "Frame {i} is {}x{}, but frames need to be at least a pixel across",
+                    frame.width, frame.height
+                ));
+            }
+            if !image.contains(frame.x, frame.y, frame.width, frame.height) {
+                let (width, height) = image.dimensions();
+                return Err(format!(
+                    "Frame {i} ({}x{} at {},{}) doesn't fit on the {width}x{height} sheet",
+                    frame.width, frame.height, frame.x, frame.y
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn default_delay() -> u16 {
+    DEFAULT_DELAY_MS
+}
+
+/// Where the sprite sheet sidecar for a given slot file lives, e.g. `0307.sprites.toml`.
+pub fn sprite_sheet_path(component_file: &Path) -> PathBuf {
+    slot_sibling(component_file, SPRITE_SHEET_EXTENSION)
+}
+
+/// Where the individual frames for a given slot file live, e.g. `0307.frames/`.
+pub fn frames_path(component_file: &Path) -> PathBuf {
+    slot_sibling(component_file, FRAMES_EXTENSION)
+}
+
+pub fn is_sprite_sheet(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(SPRITE_SHEET_EXTENSION)
+}
+
+pub fn load_sprite_sheet(path: &Path) -> Result<Option<SpriteSheet>, io::Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(path)?;
+    toml::from_str(&contents).map(Some).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+/// Cuts each frame out of the sheet into its own PNG, plus an animated preview of all of them.
+pub fn export_frames(sggg: &[u8], sheet: &SpriteSheet, frames_dir: &Path) -> Result<(), io::Error> {
+    let image = SgggImage::from_sggg(sggg)?;
+    sheet
+        .check_bounds(&image)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::create_dir_all(frames_dir)?;
+    let palette = image
+        .palette(0)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "The sheet has no palette"))?;
+
+    // The preview canvas needs to fit the largest frame. Smaller frames are anchored top left on a transparent background.
+    let canvas_width = sheet.frames.iter().map(|f| f.width).max().unwrap_or(1);
+    let canvas_height = sheet.frames.iter().map(|f| f.height).max().unwrap_or(1);
+    let mut preview_frames = Vec::with_capacity(sheet.frames.len());
+
+    for (i, frame) in sheet.frames.iter().enumerate() {
+        let pixels = image.crop(frame.x, frame.y, frame.width, frame.height);
+        let frame_path = frames_dir.join(format!("{i:03}.png"));
+        debug!("Writing frame {}", frame_path.display());
+        let mut bw = BufWriter::new(File::create(&frame_path)?);
+        write_indexed_png(&mut bw, frame.width, frame.height, palette, &[(&pixels, 0)])?;
+
+        let mut canvas = vec![0; (canvas_width * canvas_height) as usize];
+        for (canvas_row, frame_row) in canvas
+            .chunks_exact_mut(canvas_width as usize)
+            .zip(pixels.chunks_exact(frame.width as usize))
+        {
+            for (canvas_pixel, frame_pixel) in canvas_row.iter_mut().zip(frame_row) {
+                *canvas_pixel = *frame_pixel;
+            }
+        }
+        preview_frames.push((canvas, frame.delay_ms.unwrap_or(sheet.delay_ms)));
+    }
+
+    if !preview_frames.is_empty() {
+        let frames = preview_frames
+            .iter()
+            .map(|(pixels, delay)| (pixels.as_slice(), *delay))
+            .collect::<Vec<_>>();
+        let mut bw = BufWriter::new(File::create(frames_dir.join(PREVIEW_NAME))?);
+        write_indexed_png(&mut bw, canvas_width, canvas_height, palette, &frames)?;
+    }
+    info!(
+        "Sliced {} frames into {}",
+        sheet.frames.len(),
+        frames_dir.display()
+    );
+    Ok(())
+}
+
+/// Pastes each frame PNG back over its rectangle on the sheet. Frames take priority over whatever is on the sheet PNG.
+pub fn import_frames(
+    sggg: &[u8],
+    sheet: &SpriteSheet,
+    frames_dir: &Path,
+) -> Result<Vec<u8>, String> {
+    let mut image = SgggImage::from_sggg(sggg).map_err(|e| e.to_string())?;
+    sheet.check_bounds(&image)?;
+    for (i, frame) in sheet.frames.iter().enumerate() {
+        let frame_path = frames_dir.join(format!("{i:03}.png"));
+        if !frame_path.exists() {
+            debug!(
+                "No frame at {}, keeping the sheet as is",
+                frame_path.display()
+            );
+            continue;
+        }
+        let file = File::open(&frame_path).map_err(|e| e.to_string())?;
+        let mut png_reader = png::Decoder::new(BufReader::new(file))
+            .read_info()
+            .map_err(|e| format!("Error reading {}: {e}", frame_path.display()))?;
+        let info = png_reader.info();
+        if (info.width, info.height) != (frame.width, frame.height) {
+            return Err(format!(
+                "{} is {}x{}, but the sprite sheet says frame {i} is {}x{}",
+                frame_path.display(),
+                info.width,
+                info.height,
+                frame.width,
+                frame.height
+            ));
+        }
+        if !matches!(
+            info.color_type,
+            png::ColorType::Indexed | png::ColorType::Grayscale
+        ) || info.bit_depth != png::BitDepth::Eight
+        {
+            return Err(format!(
+                "{} must be an 8-bit indexed (paletted) PNG so its pixels map onto the sheet's palette",
+                frame_path.display()
+            ));
+        }
+        let mut pixels = vec![0; png_reader.output_buffer_size().unwrap()];
+        png_reader
+            .next_frame(&mut pixels)
+            .map_err(|e| format!("Error reading {}: {e}", frame_path.display()))?;
+        image.paste(frame.x, frame.y, frame.width, &pixels);
+    }
+    Ok(image.into_sggg())
+}